@@ -0,0 +1,87 @@
+//! Times building worlds from many small brushes, with and without preallocation hints, and
+//! from a few brushes filling a large world
+//!
+//! Run with `cargo bench --bench build`
+#![allow(clippy::needless_return)]
+
+use std::time::{Duration, Instant};
+
+use thermal_rs::{
+    material::{self, Material},
+    volume::AABBVolume,
+    world::SimWorldBuilder,
+};
+
+const BRUSHES: usize = 10_000;
+const MATERIALS: usize = 200;
+const BUILDS: u32 = 10;
+const LARGE_BRUSHES: usize = 12;
+
+fn time_builds(hinted: bool) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..BUILDS {
+        let start = Instant::now();
+        let mut builder = SimWorldBuilder::new(1.0, 1.0, 1.0);
+        if hinted {
+            builder = builder
+                .with_expected_brushes(BRUSHES)
+                .with_expected_materials(MATERIALS);
+        }
+        for i in 0..BRUSHES {
+            let material = Material {
+                density: 1000.0 + (i % MATERIALS) as f32,
+                ..material::WATER
+            };
+            let corner = (i % 100) as f64 / 100.0;
+            builder = builder.with_material(
+                material,
+                Box::new(AABBVolume::new(
+                    corner,
+                    corner,
+                    0.0,
+                    corner + 0.01,
+                    corner + 0.01,
+                    1.0,
+                )),
+            );
+        }
+        builder.build(0.01);
+        total += start.elapsed();
+    }
+    return total / BUILDS;
+}
+
+fn time_large_builds() -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..BUILDS {
+        let start = Instant::now();
+        let mut builder = SimWorldBuilder::new(2.56, 2.56, 2.56);
+        for i in 0..LARGE_BRUSHES {
+            let material = Material {
+                density: 1000.0 + i as f32,
+                ..material::WATER
+            };
+            builder = builder.with_material(
+                material,
+                Box::new(AABBVolume::new(0.0, 0.0, 0.0, 2.56, 2.56, 2.56)),
+            );
+        }
+        builder.build(0.01);
+        total += start.elapsed();
+    }
+    return total / BUILDS;
+}
+
+fn main() {
+    let unhinted = time_builds(false);
+    let hinted = time_builds(true);
+    println!("{BRUSHES} brushes of {MATERIALS} materials, mean build time over {BUILDS} builds");
+    println!("unhinted: {unhinted:?}");
+    println!("hinted:   {hinted:?}");
+
+    let large = time_large_builds();
+    println!(
+        "256^3 cells, {LARGE_BRUSHES} full volume brushes, mean build time over {BUILDS} builds"
+    );
+    println!("build:    {large:?}");
+}
@@ -0,0 +1,53 @@
+//! Compares the time per step of the scalar and SIMD CPU runners
+//!
+//! Run with `cargo bench --features simd`
+#![allow(clippy::needless_return)]
+
+use std::time::{Duration, Instant};
+
+use thermal_rs::{
+    material,
+    runner::{SimRunner, cpu::CPUSimRunner, simd::SimdSimRunner},
+    volume::AABBVolume,
+    world::{SimState, SimWorld, SimWorldBuilder},
+};
+
+const STEPS: u32 = 20;
+
+fn time_steps(runner: &impl SimRunner, world: &SimWorld, state: &SimState) -> Duration {
+    let timestep = world.max_stable_timestep() as f32;
+    let mut state = state.clone();
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        state = runner.advance_step(world, state, timestep);
+    }
+    return start.elapsed() / STEPS;
+}
+
+fn main() {
+    let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.64, 0.64, 0.64);
+    let world = SimWorldBuilder::new(0.64, 0.64, 0.64)
+        .with_material(material::WATER, Box::new(everything.clone()))
+        .with_material(
+            material::COPPER,
+            Box::new(AABBVolume::new(0.16, 0.16, 0.16, 0.48, 0.48, 0.48)),
+        )
+        .build(0.01);
+    let mut state = world
+        .set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything)
+        .unwrap();
+    state = world
+        .set_sim_state_temperature(
+            state,
+            400.0,
+            &AABBVolume::new(0.0, 0.0, 0.0, 0.32, 0.64, 0.64),
+        )
+        .unwrap();
+
+    let scalar = time_steps(&CPUSimRunner::default(), &world, &state);
+    let simd = time_steps(&SimdSimRunner::default(), &world, &state);
+    println!("64^3 cells, mean time per step over {STEPS} steps");
+    println!("scalar: {scalar:?}");
+    println!("simd:   {simd:?}");
+    println!("speedup: {:.2}x", scalar.as_secs_f64() / simd.as_secs_f64());
+}
@@ -0,0 +1,594 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    sensor::SensorArray,
+    world::{Float, SimState, SimWorld},
+};
+
+/// Write the temperature field of a simulation state to a legacy VTK file, for viewing in tools
+/// such as ParaView
+///
+/// The field is written as `STRUCTURED_POINTS`, with one point at the center of every cell
+/// holding its temperature in kelvin. Fails with [io::ErrorKind::InvalidInput] if the state is not
+/// a valid state of the world.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, export::write_vtk};
+/// let world = SimWorldBuilder::new(0.4, 0.3, 0.2)
+///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.4, 0.3, 0.2)))
+///     .build(0.1);
+/// let state = world
+///     .set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.4, 0.3, 0.2))
+///     .unwrap();
+///
+/// let path = std::env::temp_dir().join("thermal_rs_doctest_field.vtk");
+/// write_vtk(&world, &state, &path).unwrap();
+///
+/// let contents = std::fs::read_to_string(&path).unwrap();
+/// let mut lines = contents.lines();
+/// assert_eq!(lines.next(), Some("# vtk DataFile Version 3.0"));
+/// assert!(contents.contains("DIMENSIONS 4 3 2"));
+/// assert!(contents.contains("SPACING 0.1 0.1 0.1"));
+/// assert!(contents.contains("POINT_DATA 24"));
+///
+/// let values: Vec<f32> = lines
+///     .skip_while(|line| !line.starts_with("LOOKUP_TABLE"))
+///     .skip(1)
+///     .flat_map(|line| line.split_whitespace())
+///     .map(|value| value.parse().unwrap())
+///     .collect();
+/// assert_eq!(values.len(), 24);
+/// assert!(values.iter().all(|t| (t - 300.0).abs() < 0.01));
+/// ```
+pub fn write_vtk<E: Float>(world: &SimWorld, state: &SimState<E>, path: &Path) -> io::Result<()> {
+    let temperatures = world.temperature_field(state).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The simulation state is not a valid state of the world",
+        )
+    })?;
+    let (dx, dy, dz) = world.get_cell_size();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# vtk DataFile Version 3.0")?;
+    writeln!(writer, "thermal_rs temperature field")?;
+    writeln!(writer, "ASCII")?;
+    writeln!(writer, "DATASET STRUCTURED_POINTS")?;
+    writeln!(
+        writer,
+        "DIMENSIONS {} {} {}",
+        world.get_x_size(),
+        world.get_y_size(),
+        world.get_z_size()
+    )?;
+    writeln!(writer, "ORIGIN {} {} {}", dx / 2.0, dy / 2.0, dz / 2.0)?;
+    writeln!(writer, "SPACING {dx} {dy} {dz}")?;
+    writeln!(writer, "POINT_DATA {}", temperatures.len())?;
+    writeln!(writer, "SCALARS temperature float 1")?;
+    writeln!(writer, "LOOKUP_TABLE default")?;
+    // Cells are stored with x varying fastest, matching the VTK point order
+    for row in temperatures.chunks(world.get_x_size().max(1)) {
+        let values: Vec<String> = row.iter().map(|t| t.to_string()).collect();
+        writeln!(writer, "{}", values.join(" "))?;
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+/// Write every cell of a simulation state to a CSV file, for analysis in spreadsheets or data
+/// frames
+///
+/// A header row is followed by one row per cell with the columns `x, y, z, material_index,
+/// temperature, energy`, in the same x fastest order as the cells are stored. Fails with
+/// [io::ErrorKind::InvalidInput] if the state is not a valid state of the world.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, export::write_csv};
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 2.0, 2.0, 2.0);
+/// let world = SimWorldBuilder::new(2.0, 2.0, 2.0)
+///     .with_material(material::WATER, Box::new(everything.clone()))
+///     .build(1.0);
+/// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+///
+/// let path = std::env::temp_dir().join("thermal_rs_doctest_cells.csv");
+/// write_csv(&world, &state, &path).unwrap();
+///
+/// let contents = std::fs::read_to_string(&path).unwrap();
+/// let rows: Vec<&str> = contents.lines().collect();
+/// assert_eq!(rows.len(), 8 + 1);
+/// assert_eq!(rows[0], "x,y,z,material_index,temperature,energy");
+/// assert!(rows[2].starts_with("1,0,0,1,"));
+/// ```
+pub fn write_csv<E: Float>(world: &SimWorld, state: &SimState<E>, path: &Path) -> io::Result<()> {
+    let temperatures = world.temperature_field(state).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The simulation state is not a valid state of the world",
+        )
+    })?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "x,y,z,material_index,temperature,energy")?;
+    for (index, ((temperature, energy), material_index)) in temperatures
+        .iter()
+        .zip(state.get_energies())
+        .zip(world.get_materials())
+        .enumerate()
+    {
+        let (x, y, z) = world
+            .get_index_pos(index)
+            .expect("The state is known to match the world");
+        writeln!(
+            writer,
+            "{x},{y},{z},{material_index},{temperature},{energy}"
+        )?;
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+/// Write the readings of a sensor array to a CSV file, one row per reading time with a header
+/// of `time` followed by `sensor_i` for each sensor in order
+///
+/// Sensors recorded by [SensorArray::record] share their reading times. Sensors without a
+/// reading at a time, such as sensors added after recording started, leave that cell empty.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, sensor::SensorArray, export::write_sensor_csv};
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 2.0, 2.0, 2.0);
+/// let world = SimWorldBuilder::new(2.0, 2.0, 2.0)
+///     .with_material(material::WATER, Box::new(everything.clone()))
+///     .build(1.0);
+/// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+///
+/// let mut sensors = SensorArray::new();
+/// sensors.add_sensor(0.5, 0.5, 0.5);
+/// sensors.record(&world, &state, 0.0);
+/// sensors.add_sensor(1.5, 0.5, 0.5);
+/// sensors.record(&world, &state, 1.0);
+///
+/// let path = std::env::temp_dir().join("thermal_rs_doctest_sensors.csv");
+/// write_sensor_csv(&sensors, &path).unwrap();
+///
+/// let contents = std::fs::read_to_string(&path).unwrap();
+/// let rows: Vec<&str> = contents.lines().collect();
+/// assert_eq!(rows.len(), 2 + 1);
+/// assert_eq!(rows[0], "time,sensor_0,sensor_1");
+/// assert!(rows[1].starts_with("0,") && rows[1].ends_with(","));
+/// ```
+pub fn write_sensor_csv(sensors: &SensorArray, path: &Path) -> io::Result<()> {
+    let mut times: Vec<f64> = sensors
+        .get_sensors()
+        .iter()
+        .flat_map(|sensor| sensor.get_history().iter().map(|(time, _)| *time))
+        .collect();
+    times.sort_by(f64::total_cmp);
+    times.dedup();
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    write!(writer, "time")?;
+    for i in 0..sensors.get_sensors().len() {
+        write!(writer, ",sensor_{i}")?;
+    }
+    writeln!(writer)?;
+
+    // Walk each sensor's history alongside the sorted times, as readings are taken in time order
+    let mut positions = vec![0; sensors.get_sensors().len()];
+    for time in times {
+        write!(writer, "{time}")?;
+        for (sensor, position) in sensors.get_sensors().iter().zip(positions.iter_mut()) {
+            match sensor.get_history().get(*position) {
+                Some((reading_time, temperature)) if *reading_time == time => {
+                    write!(writer, ",{temperature}")?;
+                    *position += 1;
+                }
+                _ => write!(writer, ",")?,
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+/// Offsets of the corners of a cube of cell centers, indexed with x as the lowest bit
+static CUBE_CORNERS: [(usize, usize, usize); 8] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (0, 1, 0),
+    (1, 1, 0),
+    (0, 0, 1),
+    (1, 0, 1),
+    (0, 1, 1),
+    (1, 1, 1),
+];
+
+/// Division of a cube into six tetrahedra around its main diagonal, which splits the faces of
+/// neighboring cubes identically so the surface has no cracks
+static CUBE_TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 3, 2, 7],
+    [0, 2, 6, 7],
+    [0, 6, 4, 7],
+    [0, 4, 5, 7],
+    [0, 5, 1, 7],
+];
+
+/// Offsets of the corners of a square of neighboring cell centers in a plane
+static SQUARE_CORNERS: [(usize, usize); 4] = [(0, 0), (1, 0), (0, 1), (1, 1)];
+
+/// Division of a square into two triangles along its diagonal, which splits the edges of
+/// neighboring squares identically so contour lines have no gaps
+static SQUARE_TRIANGLES: [[usize; 3]; 2] = [[0, 1, 3], [0, 3, 2]];
+
+/// Write the surface where the temperature of a simulation state crosses a threshold to a
+/// Wavefront OBJ mesh, such as the melt front of a solidifying material
+///
+/// The surface is extracted with marching tetrahedra, a variant of marching cubes that splits
+/// every cube of neighboring cell centers into tetrahedra and has no ambiguous cases. Vertices
+/// are in world space meters, and triangles face from the hotter side to the colder side. Fails
+/// with [io::ErrorKind::InvalidInput] if the state is not a valid state of the world.
+///
+/// Planar worlds, a single cell deep, have no volume to extract a surface from, so the contour
+/// lines where the temperature crosses the threshold are written as line elements instead.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::{SimState, SimWorldBuilder}, material::{self, Axis}, volume::AABBVolume, export::isosurface_obj};
+/// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+///     .build(0.05);
+/// // A field cooling with distance from the center of the world
+/// let cell_size = world.get_cell_length(Axis::X);
+/// let state: SimState = (0..world.get_materials().len())
+///     .map(|index| {
+///         let (x, y, z) = world.get_index_pos(index).unwrap();
+///         let distance = [x, y, z]
+///             .map(|c| (c as f64 + 0.5) * cell_size - 0.5)
+///             .iter()
+///             .map(|d| d * d)
+///             .sum::<f64>()
+///             .sqrt();
+///         let temperature = 400.0 - 200.0 * distance as f32;
+///         material::COPPER.energy_in_volume(temperature, world.get_cell_volume() as f32)
+///     })
+///     .collect();
+///
+/// // The 340 K surface is a sphere of radius 0.3 m
+/// let path = std::env::temp_dir().join("thermal_rs_doctest_isosurface.obj");
+/// isosurface_obj(&world, &state, 340.0, &path).unwrap();
+///
+/// let contents = std::fs::read_to_string(&path).unwrap();
+/// let vertices: Vec<Vec<f64>> = contents
+///     .lines()
+///     .filter_map(|line| line.strip_prefix("v "))
+///     .map(|line| line.split_whitespace().map(|v| v.parse().unwrap()).collect())
+///     .collect();
+/// assert!(vertices.len() > 100);
+/// assert!(contents.lines().any(|line| line.starts_with("f ")));
+/// for vertex in vertices {
+///     let radius = vertex.iter().map(|c| (c - 0.5) * (c - 0.5)).sum::<f64>().sqrt();
+///     assert!((radius - 0.3).abs() < 0.01);
+/// }
+/// ```
+pub fn isosurface_obj<E: Float>(
+    world: &SimWorld,
+    state: &SimState<E>,
+    iso: f32,
+    path: &Path,
+) -> io::Result<()> {
+    let temperatures = world.temperature_field(state).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The simulation state is not a valid state of the world",
+        )
+    })?;
+    let (x_size, y_size, z_size) = (world.get_x_size(), world.get_y_size(), world.get_z_size());
+
+    let mut mesh = IsosurfaceMesh {
+        world,
+        temperatures: &temperatures,
+        iso,
+        vertex_indices: HashMap::new(),
+        vertices: Vec::new(),
+        triangles: Vec::new(),
+        lines: Vec::new(),
+    };
+    if world.is_planar() {
+        for y in 0..y_size.saturating_sub(1) {
+            for x in 0..x_size.saturating_sub(1) {
+                let corners = SQUARE_CORNERS.map(|(dx, dy)| {
+                    world
+                        .get_pos_index(x + dx, y + dy, 0)
+                        .expect("Square corners are within the world")
+                });
+                for triangle in SQUARE_TRIANGLES.iter() {
+                    mesh.add_triangle(triangle.map(|corner| corners[corner]));
+                }
+            }
+        }
+    }
+    for z in 0..z_size.saturating_sub(1) {
+        for y in 0..y_size.saturating_sub(1) {
+            for x in 0..x_size.saturating_sub(1) {
+                let corners = CUBE_CORNERS.map(|(dx, dy, dz)| {
+                    world
+                        .get_pos_index(x + dx, y + dy, z + dz)
+                        .expect("Cube corners are within the world")
+                });
+                for tetrahedron in CUBE_TETRAHEDRA.iter() {
+                    mesh.add_tetrahedron(tetrahedron.map(|corner| corners[corner]));
+                }
+            }
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "# thermal_rs isosurface at {iso} K")?;
+    for [x, y, z] in mesh.vertices.iter() {
+        writeln!(writer, "v {x} {y} {z}")?;
+    }
+    // OBJ indices start from 1
+    for [a, b, c] in mesh.triangles.iter() {
+        writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+    }
+    for [a, b] in mesh.lines.iter() {
+        writeln!(writer, "l {} {}", a + 1, b + 1)?;
+    }
+    writer.flush()?;
+    return Ok(());
+}
+
+/// A triangle mesh being extracted from a temperature field
+struct IsosurfaceMesh<'a> {
+    world: &'a SimWorld,
+    temperatures: &'a [f32],
+    iso: f32,
+    // Vertices are shared between triangles, keyed by the pair of cells they lie between
+    vertex_indices: HashMap<(usize, usize), usize>,
+    vertices: Vec<[f64; 3]>,
+    triangles: Vec<[usize; 3]>,
+    // Contour line segments, for planar worlds
+    lines: Vec<[usize; 2]>,
+}
+
+impl IsosurfaceMesh<'_> {
+    /// Get the world space position of the center of a cell
+    fn cell_position(&self, index: usize) -> [f64; 3] {
+        let (x, y, z) = self
+            .world
+            .get_index_pos(index)
+            .expect("Only indices within the world are used");
+        let (dx, dy, dz) = self.world.get_cell_size();
+        return [(x, dx), (y, dy), (z, dz)].map(|(c, size)| (c as f64 + 0.5) * size);
+    }
+
+    /// Get the index of the vertex where the surface crosses between two cells, adding it if it
+    /// does not exist yet
+    fn edge_vertex(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (a.min(b), a.max(b));
+        if let Some(index) = self.vertex_indices.get(&(a, b)) {
+            return *index;
+        }
+        let (ta, tb) = (self.temperatures[a], self.temperatures[b]);
+        let t = ((self.iso - ta) / (tb - ta)) as f64;
+        let (pa, pb) = (self.cell_position(a), self.cell_position(b));
+        self.vertices
+            .push([0, 1, 2].map(|c| pa[c] + t * (pb[c] - pa[c])));
+        self.vertex_indices.insert((a, b), self.vertices.len() - 1);
+        return self.vertices.len() - 1;
+    }
+
+    /// Add the part of the contour line passing through a triangle of cells in a plane
+    fn add_triangle(&mut self, cells: [usize; 3]) {
+        let (hot, cold): (Vec<usize>, Vec<usize>) = cells
+            .iter()
+            .partition(|cell| self.temperatures[**cell] >= self.iso);
+
+        let line = match (&hot[..], &cold[..]) {
+            ([h], [c0, c1]) => [self.edge_vertex(*h, *c0), self.edge_vertex(*h, *c1)],
+            ([h0, h1], [c]) => [self.edge_vertex(*h0, *c), self.edge_vertex(*h1, *c)],
+            _ => return,
+        };
+        self.lines.push(line);
+    }
+
+    /// Add the part of the surface passing through a tetrahedron of cells
+    fn add_tetrahedron(&mut self, cells: [usize; 4]) {
+        let (hot, cold): (Vec<usize>, Vec<usize>) = cells
+            .iter()
+            .partition(|cell| self.temperatures[**cell] >= self.iso);
+
+        let new_triangles: Vec<[usize; 3]> = match (&hot[..], &cold[..]) {
+            ([h], [c0, c1, c2]) => vec![[
+                self.edge_vertex(*h, *c0),
+                self.edge_vertex(*h, *c1),
+                self.edge_vertex(*h, *c2),
+            ]],
+            ([h0, h1, h2], [c]) => vec![[
+                self.edge_vertex(*h0, *c),
+                self.edge_vertex(*h1, *c),
+                self.edge_vertex(*h2, *c),
+            ]],
+            ([h0, h1], [c0, c1]) => {
+                let quad = [
+                    self.edge_vertex(*h0, *c0),
+                    self.edge_vertex(*h0, *c1),
+                    self.edge_vertex(*h1, *c1),
+                    self.edge_vertex(*h1, *c0),
+                ];
+                vec![[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]]
+            }
+            _ => return,
+        };
+
+        // Face each triangle from the hot side of the tetrahedron to the cold side
+        let (hot_point, cold_point) = (self.cell_position(hot[0]), self.cell_position(cold[0]));
+        for [a, b, c] in new_triangles {
+            let (pa, pb, pc) = (self.vertices[a], self.vertices[b], self.vertices[c]);
+            let u = [0, 1, 2].map(|i| pb[i] - pa[i]);
+            let v = [0, 1, 2].map(|i| pc[i] - pa[i]);
+            let normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let facing: f64 = (0..3)
+                .map(|i| normal[i] * (cold_point[i] - hot_point[i]))
+                .sum();
+            if facing < 0.0 {
+                self.triangles.push([a, c, b]);
+            } else {
+                self.triangles.push([a, b, c]);
+            }
+        }
+    }
+}
+
+/// Possible errors when exporting images
+#[cfg(feature = "image")]
+#[derive(Debug)]
+pub enum ImageExportError {
+    /// The simulation state is not a valid state of the world
+    StateInvalid,
+    /// The requested slice is outside the world
+    SliceOutOfBounds,
+    /// The image could not be encoded or written
+    Image(image::ImageError),
+}
+
+/// A mapping from normalized temperatures to colors, requires the `image` feature
+#[cfg(feature = "image")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Colormap {
+    /// Black for the coldest temperature to white for the hottest
+    Grayscale,
+    /// Perceptually uniform dark purple to yellow
+    Viridis,
+    /// Dark blue through green to dark red
+    Jet,
+}
+
+/// Anchor colors of the viridis colormap, evenly spaced from 0.0 to 1.0
+#[cfg(feature = "image")]
+static VIRIDIS: [[u8; 3]; 5] = [
+    [68, 1, 84],
+    [59, 82, 139],
+    [33, 145, 140],
+    [94, 201, 98],
+    [253, 231, 37],
+];
+
+#[cfg(feature = "image")]
+impl Colormap {
+    /// Get the RGB color for a value between 0.0 and 1.0, values outside that range are clamped
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::export::Colormap;
+    /// assert_eq!(Colormap::Grayscale.color(0.0), [0, 0, 0]);
+    /// assert_eq!(Colormap::Grayscale.color(1.0), [255, 255, 255]);
+    /// assert_eq!(Colormap::Jet.color(0.0), [0, 0, 128]);
+    /// assert_eq!(Colormap::Jet.color(2.0), [128, 0, 0]);
+    /// ```
+    pub fn color(&self, value: f32) -> [u8; 3] {
+        let value = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        let to_byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+        return match self {
+            Colormap::Grayscale => [to_byte(value); 3],
+            Colormap::Viridis => {
+                let position = value * (VIRIDIS.len() - 1) as f32;
+                let lower = (position.floor() as usize).min(VIRIDIS.len() - 2);
+                let fraction = position - lower as f32;
+                let (from, to) = (VIRIDIS[lower], VIRIDIS[lower + 1]);
+                [0, 1, 2].map(|c| {
+                    (from[c] as f32 + (to[c] as f32 - from[c] as f32) * fraction).round() as u8
+                })
+            }
+            Colormap::Jet => [
+                to_byte(1.5 - (4.0 * value - 3.0).abs()),
+                to_byte(1.5 - (4.0 * value - 2.0).abs()),
+                to_byte(1.5 - (4.0 * value - 1.0).abs()),
+            ],
+        };
+    }
+}
+
+/// Write a slice of constant z through the temperature field of a simulation state to a PNG
+/// heatmap, requires the `image` feature
+///
+/// The image is `x_size` by `y_size` pixels, with y increasing downwards. Temperatures are
+/// normalized between the minimum and maximum temperature in the slice, so a uniform slice is
+/// drawn entirely in the coldest color of the colormap.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, export::{write_slice_png, Colormap, ImageExportError}};
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.4, 0.3, 0.2);
+/// let world = SimWorldBuilder::new(0.4, 0.3, 0.2)
+///     .with_material(material::WATER, Box::new(everything.clone()))
+///     .build(0.1);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+/// let path = std::env::temp_dir().join("thermal_rs_doctest_slice.png");
+///
+/// // A uniform field is a single color
+/// write_slice_png(&world, &state, 0, Colormap::Viridis, &path).unwrap();
+/// let image = image::open(&path).unwrap().to_rgb8();
+/// assert_eq!(image.dimensions(), (4, 3));
+/// assert!(image.pixels().all(|p| p.0 == Colormap::Viridis.color(0.0)));
+///
+/// // The coldest and hottest cells map to the ends of the colormap
+/// state = world.set_sim_state_temperature(state, 350.0, &AABBVolume::new(0.3, 0.2, 0.0, 0.4, 0.3, 0.1)).unwrap();
+/// write_slice_png(&world, &state, 0, Colormap::Viridis, &path).unwrap();
+/// let image = image::open(&path).unwrap().to_rgb8();
+/// assert_eq!(image.get_pixel(0, 0).0, Colormap::Viridis.color(0.0));
+/// assert_eq!(image.get_pixel(3, 2).0, Colormap::Viridis.color(1.0));
+///
+/// let out_of_bounds = write_slice_png(&world, &state, 2, Colormap::Viridis, &path);
+/// assert!(matches!(out_of_bounds, Err(ImageExportError::SliceOutOfBounds)));
+/// ```
+#[cfg(feature = "image")]
+pub fn write_slice_png<E: Float>(
+    world: &SimWorld,
+    state: &SimState<E>,
+    z: usize,
+    colormap: Colormap,
+    path: &Path,
+) -> Result<(), ImageExportError> {
+    if z >= world.get_z_size() {
+        return Err(ImageExportError::SliceOutOfBounds);
+    }
+    let temperatures = world
+        .temperature_field(state)
+        .map_err(|_| ImageExportError::StateInvalid)?;
+    let (x_size, y_size) = (world.get_x_size(), world.get_y_size());
+    let slice = &temperatures[z * x_size * y_size..(z + 1) * x_size * y_size];
+
+    let min = slice.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = slice.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+
+    let mut image = image::RgbImage::new(x_size as u32, y_size as u32);
+    for (index, temperature) in slice.iter().enumerate() {
+        let normalized = if range > 0.0 {
+            (temperature - min) / range
+        } else {
+            0.0
+        };
+        let (x, y) = (index % x_size, index / x_size);
+        image.put_pixel(x as u32, y as u32, image::Rgb(colormap.color(normalized)));
+    }
+    return image.save(path).map_err(ImageExportError::Image);
+}
@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use crate::{volume::CellIterator, world::BuildError};
+
+/// Possible errors when importing a world from a MagicaVoxel `.vox` file
+#[derive(Debug)]
+pub enum VoxError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file does not start with the `.vox` file signature
+    NotVox,
+    /// A chunk of the file is cut short or has an invalid size
+    Malformed,
+    /// The file does not contain a model
+    NoModel,
+    /// The model or the resolution or materials it is imported with are not a valid world
+    Build(BuildError),
+}
+
+/// The first model of a `.vox` file
+pub(crate) struct VoxModel {
+    // The (x, y, z) dimensions of the model, in voxels
+    pub(crate) size: (usize, usize, usize),
+    // The position and palette index of every filled voxel
+    pub(crate) voxels: Vec<((usize, usize, usize), u8)>,
+}
+
+/// Read the first model of a MagicaVoxel `.vox` file
+///
+/// Only the `SIZE` and `XYZI` chunks of the first model are used, any other chunks, such as the
+/// palette, scene graph and further models, are skipped.
+pub(crate) fn read_vox(path: &Path) -> Result<VoxModel, VoxError> {
+    let bytes = std::fs::read(path).map_err(VoxError::Io)?;
+    if bytes.len() < 8 || &bytes[0..4] != b"VOX " {
+        return Err(VoxError::NotVox);
+    }
+
+    let read_u32 = |offset: usize| -> Result<usize, VoxError> {
+        let word = bytes.get(offset..offset + 4).ok_or(VoxError::Malformed)?;
+        return Ok(u32::from_le_bytes([word[0], word[1], word[2], word[3]]) as usize);
+    };
+
+    let mut size = None;
+    let mut voxels = None;
+    let mut offset = 8;
+    while offset < bytes.len() && (size.is_none() || voxels.is_none()) {
+        let id = bytes.get(offset..offset + 4).ok_or(VoxError::Malformed)?;
+        let content_size = read_u32(offset + 4)?;
+        let children_size = read_u32(offset + 8)?;
+        let content_start = offset + 12;
+        let content = bytes
+            .get(content_start..content_start + content_size)
+            .ok_or(VoxError::Malformed)?;
+
+        match id {
+            b"SIZE" if size.is_none() => {
+                size = Some((
+                    read_u32(content_start)?,
+                    read_u32(content_start + 4)?,
+                    read_u32(content_start + 8)?,
+                ));
+            }
+            b"XYZI" if voxels.is_none() => {
+                let count = read_u32(content_start)?;
+                let data = content.get(4..4 + count * 4).ok_or(VoxError::Malformed)?;
+                voxels = Some(
+                    data.chunks_exact(4)
+                        .map(|v| ((v[0] as usize, v[1] as usize, v[2] as usize), v[3]))
+                        .collect(),
+                );
+            }
+            _ => {}
+        }
+
+        // The children of the MAIN chunk hold the models, so step into them rather than over them
+        offset = content_start + content_size;
+        if id != b"MAIN" {
+            offset += children_size;
+        }
+    }
+
+    return match (size, voxels) {
+        (Some(size), Some(voxels)) => Ok(VoxModel { size, voxels }),
+        _ => Err(VoxError::NoModel),
+    };
+}
+
+/// A volume of a list of cells, ignoring the cell size it is iterated with
+pub(crate) struct CellList(pub(crate) Vec<(usize, usize, usize)>);
+
+impl CellIterator for CellList {
+    fn cell_iter(
+        &self,
+        _cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        return Box::new(self.0.clone().into_iter());
+    }
+}
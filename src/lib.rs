@@ -1,11 +1,18 @@
 #![deny(missing_docs)]
 #![allow(unused)]
+#![allow(clippy::needless_return)]
 #![doc = include_str!("../README.md")]
 
+/// Export of simulation results to external file formats
+pub mod export;
+/// Import of simulation worlds from external file formats
+pub mod import;
 /// Definition for simulation materials
 pub mod material;
 /// Definition of simulation runners
 pub mod runner;
+/// Virtual sensors recording temperatures over a simulation
+pub mod sensor;
 /// Definition of sim volumes and brushes
 pub mod volume;
 
@@ -1,47 +1,1016 @@
 use std::hash::Hash;
 
+/// The Stefan-Boltzmann constant in W / m^2 K^4
+pub const STEFAN_BOLTZMANN: f32 = 5.670374e-8;
+
 /// Default blank material
 pub static BLANK: Material = Material {
     density: 1000.0,
-    specific_heat: 1000.0,
-    thermal_conductivity: (0.0, 0.0, 0.0),
+    specific_heat: (0.0, 0.0, 1000.0),
+    thermal_conductivity: [(0.0, 0.0, 0.0); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
 };
 
 /// Default material aproximating the properties of water at sea level atmospheric pressure
 pub static WATER: Material = Material {
     density: 1000.0,
-    specific_heat: 4000.0,
-    thermal_conductivity: (-0.000006454, 0.005208, -0.3686),
+    specific_heat: (0.0, 0.0, 4000.0),
+    thermal_conductivity: [(-0.000006454, 0.005208, -0.3686); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: 273.15,
+    boiling_point: 373.15,
+    latent_heat_fusion: 334000.0,
+    latent_heat_vaporization: 2256000.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
+};
+
+/// Default material aproximating the properties of dry air at sea level atmospheric pressure
+///
+/// The thermal conductivity fit is valid from 250 K to 400 K, phase changes are not modeled.
+pub static AIR: Material = Material {
+    density: 1.2,
+    specific_heat: (0.0, 0.0, 1005.0),
+    thermal_conductivity: [(-0.00000002, 0.0000894, 0.00122); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
+};
+
+/// Default material aproximating the properties of pure copper
+///
+/// The thermal conductivity fit is valid from 250 K to 400 K, phase changes are not modeled.
+pub static COPPER: Material = Material {
+    density: 8960.0,
+    specific_heat: (0.0, 0.0, 385.0),
+    thermal_conductivity: [(0.0002, -0.218, 448.1); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
+};
+
+/// Default material aproximating the properties of pure aluminum
+///
+/// The thermal conductivity fit is valid from 250 K to 400 K, phase changes are not modeled.
+pub static ALUMINUM: Material = Material {
+    density: 2700.0,
+    specific_heat: (0.0, 0.0, 897.0),
+    thermal_conductivity: [(-0.0001, 0.099, 216.45); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
+};
+
+/// Default material aproximating the properties of plain carbon steel
+///
+/// The thermal conductivity fit is valid from 250 K to 400 K, phase changes are not modeled.
+pub static STEEL: Material = Material {
+    density: 7850.0,
+    specific_heat: (0.0, 0.0, 490.0),
+    thermal_conductivity: [(-0.00005, -0.0165, 73.275); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
 };
 
+/// Default material aproximating the properties of normal weight concrete
+///
+/// The thermal conductivity fit is valid from 250 K to 400 K, phase changes are not modeled.
+pub static CONCRETE: Material = Material {
+    density: 2300.0,
+    specific_heat: (0.0, 0.0, 880.0),
+    thermal_conductivity: [(-0.000001, 0.00131, 1.0955); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
+};
+
+/// Default material aproximating the properties of soda lime glass
+///
+/// The thermal conductivity fit is valid from 250 K to 400 K, phase changes are not modeled.
+pub static GLASS: Material = Material {
+    density: 2500.0,
+    specific_heat: (0.0, 0.0, 840.0),
+    thermal_conductivity: [(0.000001, 0.00029, 0.8245); 3],
+    expansion_coeff: 0.0,
+    reference_temp: 293.15,
+    melting_point: f32::INFINITY,
+    boiling_point: f32::INFINITY,
+    latent_heat_fusion: 0.0,
+    latent_heat_vaporization: 0.0,
+    emissivity: 0.0,
+    convective_coefficient: 0.0,
+};
+
+/// A world space axis
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Axis {
+    /// The x axis
+    X,
+    /// The y axis
+    Y,
+    /// The z axis
+    Z,
+}
+
+/// Possible errors when fitting material coefficients to measurements
+#[derive(Debug)]
+pub enum FitError {
+    /// Fewer points were given than the fit has coefficients
+    TooFewPoints,
+    /// The points do not determine a unique fit, such as when they share too few temperatures
+    Degenerate,
+}
+
 /// Represents a material type
-#[derive(Debug, PartialEq, Clone, Copy)]
+///
+/// Along with [BLANK], a handful of common materials are provided as presets.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+/// // A copper block sitting in a half meter cube of air
+/// let world = SimWorldBuilder::new(0.5, 0.5, 0.5)
+///     .with_material(material::AIR, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.5, 0.5, 0.5)))
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.2, 0.0, 0.2, 0.3, 0.1, 0.3)))
+///     .build(0.1);
+/// assert_eq!(world.get_material_map().len(), 3);
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
 pub struct Material {
     /// The density of the material in kg/m^3
     pub density: f32,
-    /// The specific heat of the material in J / K * kg
-    pub specific_heat: f32,
-    /// Coefficients (a, b, c) for an equation for thermal conductivity C of the material in W / m K. Given as C = a*k^2 + b*c + c
-    pub thermal_conductivity: (f32, f32, f32),
+    /// Coefficients (a, b, c) for an equation for specific heat C of the material in J / K * kg. Given as C = a*t^2 + b*t + c
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "coefficients::deserialize")
+    )]
+    pub specific_heat: (f32, f32, f32),
+    /// Coefficients (a, b, c) for an equation for thermal conductivity C of the material in W / m K along each [Axis]. Given as C = a*k^2 + b*c + c
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "coefficients::deserialize_axes")
+    )]
+    pub thermal_conductivity: [(f32, f32, f32); 3],
+    /// The linear thermal expansion coefficient of the material in 1 / K
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub expansion_coeff: f32,
+    /// The temperature in kelvin at which the material has its nominal density
+    #[cfg_attr(feature = "serde", serde(default = "default_reference_temp"))]
+    pub reference_temp: f32,
+    /// The melting point of the material in kelvin, a non finite value disables melting
+    #[cfg_attr(feature = "serde", serde(default = "default_phase_point"))]
+    pub melting_point: f32,
+    /// The boiling point of the material in kelvin, a non finite value disables boiling
+    #[cfg_attr(feature = "serde", serde(default = "default_phase_point"))]
+    pub boiling_point: f32,
+    /// The latent heat of fusion of the material in J / kg
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub latent_heat_fusion: f32,
+    /// The latent heat of vaporization of the material in J / kg
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub latent_heat_vaporization: f32,
+    /// The emissivity of the material surface, from 0.0 for no radiation to 1.0 for a black body
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub emissivity: f32,
+    /// The convective heat transfer coefficient in W / m^2 K of faces bordering the world's
+    /// default material, see [crate::world::SimWorldBuilder::with_ambient_temperature]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub convective_coefficient: f32,
 }
 
 impl Material {
+    /// Create a builder for a new material, starting from the properties of [BLANK]
+    pub fn builder() -> MaterialBuilder {
+        MaterialBuilder { material: BLANK }
+    }
+
+    /// Create the effective material of a mixture, with `fraction_a` of its volume made of
+    /// material `a` and the rest of material `b`. The fraction is clamped to 0..=1
+    ///
+    /// Density, conductivity and the remaining properties are weighted by volume, while the
+    /// specific heat is weighted by mass so that the mixture holds the same energy as its parts.
+    /// A mixture can only have one melting and one boiling point, so where the two materials
+    /// differ the mixture takes the phase change of the material with the larger volume, with
+    /// its latent heat scaled by that material's share of the mass.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Material};
+    /// assert_eq!(Material::mix(&material::WATER, &material::WATER, 0.3), material::WATER);
+    ///
+    /// let half = Material::mix(&material::WATER, &material::COPPER, 0.5);
+    /// assert_eq!(half.density, (material::WATER.density + material::COPPER.density) / 2.0);
+    /// assert_eq!(Material::mix(&material::WATER, &material::COPPER, 1.5), material::WATER);
+    /// ```
+    pub fn mix(a: &Material, b: &Material, fraction_a: f32) -> Material {
+        let volume_fraction = fraction_a.clamp(0.0, 1.0);
+        // Interpolate from b to a, exact when both values are the same or at either end
+        let lerp = |a: f32, b: f32, fraction: f32| {
+            if a == b {
+                return a;
+            }
+            return a * fraction + b * (1.0 - fraction);
+        };
+        let lerp_coefficients = |a: (f32, f32, f32), b: (f32, f32, f32), fraction: f32| {
+            (
+                lerp(a.0, b.0, fraction),
+                lerp(a.1, b.1, fraction),
+                lerp(a.2, b.2, fraction),
+            )
+        };
+
+        let density = lerp(a.density, b.density, volume_fraction);
+        let mass_fraction = if density > 0.0 {
+            volume_fraction * a.density / density
+        } else {
+            volume_fraction
+        };
+        let phase_change = |a_change: (f32, f32), b_change: (f32, f32)| -> (f32, f32) {
+            if a_change.0 == b_change.0 {
+                return (a_change.0, lerp(a_change.1, b_change.1, mass_fraction));
+            }
+            if volume_fraction >= 0.5 {
+                return (a_change.0, a_change.1 * mass_fraction);
+            }
+            return (b_change.0, b_change.1 * (1.0 - mass_fraction));
+        };
+        let (melting_point, latent_heat_fusion) = phase_change(
+            (a.melting_point, a.latent_heat_fusion),
+            (b.melting_point, b.latent_heat_fusion),
+        );
+        let (boiling_point, latent_heat_vaporization) = phase_change(
+            (a.boiling_point, a.latent_heat_vaporization),
+            (b.boiling_point, b.latent_heat_vaporization),
+        );
+
+        return Material {
+            density,
+            specific_heat: lerp_coefficients(a.specific_heat, b.specific_heat, mass_fraction),
+            thermal_conductivity: [0, 1, 2].map(|axis| {
+                lerp_coefficients(
+                    a.thermal_conductivity[axis],
+                    b.thermal_conductivity[axis],
+                    volume_fraction,
+                )
+            }),
+            expansion_coeff: lerp(a.expansion_coeff, b.expansion_coeff, volume_fraction),
+            reference_temp: lerp(a.reference_temp, b.reference_temp, volume_fraction),
+            melting_point,
+            boiling_point,
+            latent_heat_fusion,
+            latent_heat_vaporization,
+            emissivity: lerp(a.emissivity, b.emissivity, volume_fraction),
+            convective_coefficient: lerp(
+                a.convective_coefficient,
+                b.convective_coefficient,
+                volume_fraction,
+            ),
+        };
+    }
+
+    /// Fit thermal conductivity coefficients to `(temperature, conductivity)` measurements
+    ///
+    /// The returned `(a, b, c)` coefficients give the quadratic `a * T^2 + b * T + c` with the
+    /// least squared error from the measurements, in the form used by
+    /// [Material::thermal_conductivity] and [MaterialBuilder::thermal_conductivity]. At least
+    /// three points at three different temperatures are needed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Material};
+    /// let (a, b, c) = material::WATER.thermal_conductivity[0];
+    /// let points: Vec<(f32, f32)> = (0..8)
+    ///     .map(|i| 275.0 + i as f32 * 12.5)
+    ///     .map(|t| (t, material::WATER.get_thermal_conductivity(t)))
+    ///     .collect();
+    ///
+    /// let (fit_a, fit_b, fit_c) = Material::fit_conductivity(&points).unwrap();
+    /// assert!((fit_a - a).abs() <= 1e-3 * a.abs());
+    /// assert!((fit_b - b).abs() <= 1e-3 * b.abs());
+    /// assert!((fit_c - c).abs() <= 1e-3 * c.abs());
+    ///
+    /// assert!(Material::fit_conductivity(&points[..2]).is_err());
+    /// ```
+    pub fn fit_conductivity(points: &[(f32, f32)]) -> Result<(f32, f32, f32), FitError> {
+        if points.len() < 3 {
+            return Err(FitError::TooFewPoints);
+        }
+
+        // Fit against temperatures centered and scaled around their mean, which keeps the normal
+        // equations well conditioned for temperatures in the hundreds of kelvin
+        let count = points.len() as f64;
+        let mean = points.iter().map(|(t, _)| *t as f64).sum::<f64>() / count;
+        let scale = points
+            .iter()
+            .map(|(t, _)| (*t as f64 - mean).abs())
+            .fold(0.0, f64::max);
+        if scale == 0.0 {
+            return Err(FitError::Degenerate);
+        }
+
+        // Normal equations of the fit k = p * u^2 + q * u + r, with u the scaled temperature
+        let mut powers = [0.0; 5];
+        let mut moments = [0.0; 3];
+        for (t, k) in points {
+            let u = (*t as f64 - mean) / scale;
+            for (n, power) in powers.iter_mut().enumerate() {
+                *power += u.powi(n as i32);
+            }
+            for (n, moment) in moments.iter_mut().enumerate() {
+                *moment += *k as f64 * u.powi(n as i32);
+            }
+        }
+        let matrix = [
+            [powers[4], powers[3], powers[2]],
+            [powers[3], powers[2], powers[1]],
+            [powers[2], powers[1], powers[0]],
+        ];
+        let rhs = [moments[2], moments[1], moments[0]];
+
+        // Solve by Cramer's rule, replacing one column of the matrix with the right hand side
+        let determinant = |m: [[f64; 3]; 3]| {
+            m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+        };
+        let full = determinant(matrix);
+        if full.abs() <= f64::EPSILON * powers[0].powi(3) {
+            return Err(FitError::Degenerate);
+        }
+        let [p, q, r] = [0, 1, 2].map(|column| {
+            let mut replaced = matrix;
+            for row in 0..3 {
+                replaced[row][column] = rhs[row];
+            }
+            determinant(replaced) / full
+        });
+
+        // Expand back into coefficients of the unscaled temperature
+        let a = p / scale.powi(2);
+        let b = q / scale - 2.0 * a * mean;
+        let c = a * mean.powi(2) - q * mean / scale + r;
+        return Ok((a as f32, b as f32, c as f32));
+    }
+
     /// Get the termal conductivity at the given termperature in kelvin
+    ///
+    /// For anisotropic materials this is the conductivity along the X axis.
     pub fn get_thermal_conductivity(&self, temp: f32) -> f32 {
-        self.thermal_conductivity.0 * temp.powf(2.0)
-            + self.thermal_conductivity.1 * temp
-            + self.thermal_conductivity.2
+        self.get_thermal_conductivity_axis(temp, Axis::X)
+    }
+
+    /// Get the termal conductivity along the given axis at the given termperature in kelvin
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Axis, Material};
+    /// // A layered material which conducts poorly across its layers
+    /// let layered = Material {
+    ///     thermal_conductivity: [(0.0, 0.0, 10.0), (0.0, 0.0, 1.0), (0.0, 0.0, 10.0)],
+    ///     ..material::BLANK
+    /// };
+    /// assert_eq!(layered.get_thermal_conductivity_axis(300.0, Axis::X), 10.0);
+    /// assert_eq!(layered.get_thermal_conductivity_axis(300.0, Axis::Y), 1.0);
+    /// ```
+    pub fn get_thermal_conductivity_axis(&self, temp: f32, axis: Axis) -> f32 {
+        let (a, b, c) = self.thermal_conductivity[axis as usize];
+        a * temp.powf(2.0) + b * temp + c
+    }
+
+    /// Get the specific heat at the given temperature in kelvin
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Material};
+    /// let material = Material {
+    ///     specific_heat: (0.0, 2.0, 1000.0),
+    ///     ..material::BLANK
+    /// };
+    /// assert_eq!(material.get_specific_heat(300.0), 1600.0);
+    /// assert_eq!(material.get_specific_heat(350.0), 1700.0);
+    /// ```
+    pub fn get_specific_heat(&self, temp: f32) -> f32 {
+        self.specific_heat.0 * temp.powf(2.0) + self.specific_heat.1 * temp + self.specific_heat.2
+    }
+
+    /// Get the density in kg/m^3 at the given temperature in kelvin
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Material};
+    /// let material = Material {
+    ///     expansion_coeff: 0.0002,
+    ///     ..material::WATER
+    /// };
+    /// assert!(material.get_density(350.0) < material.get_density(300.0));
+    /// ```
+    pub fn get_density(&self, temp: f32) -> f32 {
+        self.density / (1.0 + self.expansion_coeff * (temp - self.reference_temp))
+    }
+
+    /// Get the volumetric heat capacity in J / (m^3 K), the density times the specific heat, at
+    /// the reference temperature
+    ///
+    /// This is the energy needed to warm a cubic meter of the material by one kelvin, and the
+    /// denominator of [Material::get_thermal_diffusivity].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material;
+    /// assert_eq!(material::WATER.volumetric_heat_capacity(), 4.0e6);
+    /// ```
+    pub fn volumetric_heat_capacity(&self) -> f32 {
+        self.density * self.get_specific_heat(self.reference_temp)
+    }
+
+    /// Get the thermal diffusivity in m^2 / s at the given temperature in kelvin
+    ///
+    /// For anisotropic materials this is the diffusivity along the most conductive axis.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material;
+    /// let diffusivity = material::WATER.get_thermal_diffusivity(293.15);
+    /// assert!(diffusivity > 1.0e-7 && diffusivity < 2.0e-7);
+    /// ```
+    pub fn get_thermal_diffusivity(&self, temp: f32) -> f32 {
+        let conductivity = [Axis::X, Axis::Y, Axis::Z]
+            .into_iter()
+            .map(|axis| self.get_thermal_conductivity_axis(temp, axis))
+            .fold(0.0, f32::max);
+        conductivity / (self.get_density(temp) * self.get_specific_heat(temp))
+    }
+
+    /// Check whether this material can hold thermal energy, it needs both a positive density and a
+    /// positive specific heat at its reference temperature
+    ///
+    /// Materials without heat capacity, such as a vacuum, have no defined temperature. Worlds
+    /// report them at their ambient temperature, and runners do not conduct heat through them.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let vacuum = Material { density: 0.0, ..material::COPPER };
+    /// assert!(!vacuum.has_heat_capacity());
+    /// assert!(material::BLANK.has_heat_capacity());
+    ///
+    /// // Hot copper separated from cold copper by a vacuum
+    /// let world = SimWorldBuilder::new(0.03, 0.01, 0.01)
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.03, 0.01, 0.01)))
+    ///     .with_material(vacuum, Box::new(AABBVolume::new(0.01, 0.0, 0.0, 0.02, 0.01, 0.01)))
+    ///     .with_ambient_temperature(250.0)
+    ///     .build(0.01);
+    /// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.03, 0.01, 0.01)).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01)).unwrap();
+    ///
+    /// let result = block_on(CPUSimRunner::default().advance_simulation(&world, &state, 10.0, world.max_stable_timestep())).unwrap();
+    /// let temperatures = world.temperature_field(&result).unwrap();
+    /// assert!(temperatures.iter().all(|t| t.is_finite()));
+    /// assert!((temperatures[0] - 400.0).abs() < 0.01);
+    /// assert_eq!(temperatures[1], 250.0);
+    /// assert!((temperatures[2] - 300.0).abs() < 0.01);
+    /// ```
+    pub fn has_heat_capacity(&self) -> bool {
+        self.density > 0.0 && self.get_specific_heat(self.reference_temp) > 0.0
+    }
+
+    /// Get the thermal energy in joules held by a given mass of this material at the given
+    /// temperature in kelvin
+    ///
+    /// Energy is measured from absolute zero, so this is the integral of the specific heat
+    /// over 0..temp, plus the latent heat of every phase change below the given temperature,
+    /// scaled by the mass. A material exactly at a phase change point is taken to be at the start
+    /// of the plateau.
+    pub fn energy_from_temperature(&self, temp: f32, mass: f32) -> f32 {
+        let latent: f32 = self
+            .phase_changes()
+            .filter(|(point, _)| temp > *point)
+            .map(|(_, heat)| heat)
+            .sum();
+        mass * (self.sensible_energy(temp) + latent)
+    }
+
+    /// Get the temperature in kelvin of a given mass of this material holding the given thermal
+    /// energy in joules. This is the inverse of [Material::energy_from_temperature]
+    ///
+    /// Within a phase change plateau, any energy between the start and the end of the plateau
+    /// maps to the phase change temperature.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material;
+    /// let melt_start = material::WATER.energy_from_temperature(273.15, 1.0);
+    /// let melt_end = melt_start + material::WATER.latent_heat_fusion;
+    /// assert_eq!(material::WATER.temperature_from_energy(melt_end, 1.0), 273.15);
+    /// assert!(material::WATER.temperature_from_energy(melt_end + 4000.0, 1.0) > 273.15);
+    /// ```
+    pub fn temperature_from_energy(&self, energy: f32, mass: f32) -> f32 {
+        return self.temperature_from_energy_f64(energy as f64, mass as f64) as f32;
+    }
+
+    /// Get the temperature in kelvin of a given mass of this material holding the given thermal
+    /// energy in joules, calculated in double precision. See [Material::temperature_from_energy]
+    pub fn temperature_from_energy_f64(&self, energy: f64, mass: f64) -> f64 {
+        let mut specific_energy = energy / mass;
+        for (point, heat) in self.phase_changes() {
+            let plateau_start = self.sensible_energy_f64(point as f64);
+            if specific_energy <= plateau_start {
+                break;
+            }
+            if specific_energy <= plateau_start + heat as f64 {
+                return point as f64;
+            }
+            specific_energy -= heat as f64;
+        }
+        return self.sensible_temperature(specific_energy);
+    }
+
+    /// Get the net radiative heat flux in W / m^2 emitted by a surface of this material at the
+    /// given temperature into surroundings at the given ambient temperature, both in kelvin
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Material};
+    /// let black_body = Material {
+    ///     emissivity: 1.0,
+    ///     ..material::BLANK
+    /// };
+    /// assert!(black_body.radiative_flux(400.0, 300.0) > 0.0);
+    /// assert_eq!(material::BLANK.radiative_flux(400.0, 300.0), 0.0);
+    /// ```
+    pub fn radiative_flux(&self, temp: f32, ambient: f32) -> f32 {
+        self.emissivity * STEFAN_BOLTZMANN * (temp.powf(4.0) - ambient.powf(4.0))
+    }
+
+    /// Get the enabled phase changes of this material as (temperature, latent heat) pairs in
+    /// ascending order of temperature
+    fn phase_changes(&self) -> impl Iterator<Item = (f32, f32)> {
+        let mut changes = [
+            (self.melting_point, self.latent_heat_fusion),
+            (self.boiling_point, self.latent_heat_vaporization),
+        ];
+        if changes[1].0 < changes[0].0 {
+            changes.swap(0, 1);
+        }
+        changes.into_iter().filter(|(point, _)| point.is_finite())
+    }
+
+    /// Get the sensible heat per kg of this material at the given temperature, ignoring phase
+    /// changes
+    fn sensible_energy(&self, temp: f32) -> f32 {
+        let (a, b, c) = self.specific_heat;
+        a * temp.powf(3.0) / 3.0 + b * temp.powf(2.0) / 2.0 + c * temp
+    }
+
+    /// Get the sensible heat per kg of this material at the given temperature, ignoring phase
+    /// changes, in double precision
+    fn sensible_energy_f64(&self, temp: f64) -> f64 {
+        let (a, b, c) = self.specific_heat;
+        let (a, b, c) = (a as f64, b as f64, c as f64);
+        a * temp.powi(3) / 3.0 + b * temp.powi(2) / 2.0 + c * temp
+    }
+
+    /// Get the temperature at which this material holds the given sensible heat per kg, in double
+    /// precision
+    fn sensible_temperature(&self, specific_energy: f64) -> f64 {
+        let (a, b, c) = self.specific_heat;
+        let (a, b, c) = (a as f64, b as f64, c as f64);
+        if a == 0.0 && b == 0.0 {
+            return specific_energy / c;
+        }
+
+        // Specific heat varies with temperature, so invert the energy polynomial with newtons
+        // method, starting from the constant term estimate
+        let mut temp = if c > 0.0 { specific_energy / c } else { 1.0 };
+        for _ in 0..64 {
+            let slope = a * temp * temp + b * temp + c;
+            if slope <= 0.0 {
+                break;
+            }
+            let step = (self.sensible_energy_f64(temp) - specific_energy) / slope;
+            temp -= step;
+            if step.abs() <= 1e-12 * temp.abs().max(1.0) {
+                break;
+            }
+        }
+        return temp;
+    }
+
+    /// Get the thermal energy in joules held by a given volume in m^3 of this material at the
+    /// given temperature in kelvin, taking into account the density at that temperature
+    pub fn energy_in_volume(&self, temp: f32, volume: f32) -> f32 {
+        self.energy_from_temperature(temp, self.get_density(temp) * volume)
+    }
+
+    /// Get the temperature in kelvin of a given volume in m^3 of this material holding the given
+    /// thermal energy in joules, taking into account the density at that temperature. This is
+    /// the inverse of [Material::energy_in_volume]
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material::{self, Material};
+    /// let expanding = Material {
+    ///     expansion_coeff: 0.001,
+    ///     ..material::COPPER
+    /// };
+    /// for temp in [100.0, 300.0, 600.0, 900.0] {
+    ///     let energy = expanding.energy_in_volume(temp, 1.0e-6);
+    ///     assert!((expanding.temperature_in_volume(energy, 1.0e-6) - temp).abs() < 0.01);
+    /// }
+    /// ```
+    pub fn temperature_in_volume(&self, energy: f32, volume: f32) -> f32 {
+        return self.temperature_in_volume_f64(energy as f64, volume as f64) as f32;
+    }
+
+    /// Get the temperature in kelvin of a given volume in m^3 of this material holding the given
+    /// thermal energy in joules, calculated in double precision. See
+    /// [Material::temperature_in_volume]
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::material;
+    /// // A hundred thousandth of a kelvin is below single precision at 1000 K, but not double
+    /// let energy = material::COPPER.energy_in_volume(1000.0, 1.0e-6) as f64;
+    /// let warmer = energy + material::COPPER.volumetric_heat_capacity() as f64 * 1.0e-6 * 1.0e-5;
+    /// let difference = material::COPPER.temperature_in_volume_f64(warmer, 1.0e-6)
+    ///     - material::COPPER.temperature_in_volume_f64(energy, 1.0e-6);
+    /// assert!((difference - 1.0e-5).abs() < 1.0e-8);
+    /// ```
+    pub fn temperature_in_volume_f64(&self, energy: f64, volume: f64) -> f64 {
+        let density = |temp: f64| {
+            self.density as f64
+                / (1.0 + self.expansion_coeff as f64 * (temp - self.reference_temp as f64))
+        };
+        let mut temp = self.temperature_from_energy_f64(energy, self.density as f64 * volume);
+        if self.expansion_coeff == 0.0 {
+            return temp;
+        }
+        // Mass depends on temperature, so refine the estimate at the nominal density until the
+        // temperature and the density agree
+        for _ in 0..64 {
+            let next = self.temperature_from_energy_f64(energy, density(temp) * volume);
+            let step = next - temp;
+            temp = next;
+            if step.abs() <= 1e-12 * temp.abs().max(1.0) {
+                break;
+            }
+        }
+        return temp;
+    }
+}
+
+/// A builder for materials
+///
+/// Any property which is not set keeps the value of [BLANK].
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::material::Material;
+/// let from_constant = Material::builder()
+///     .density(8960.0)
+///     .specific_heat(385.0)
+///     .constant_conductivity(401.0)
+///     .build();
+/// let from_coefficients = Material::builder()
+///     .density(8960.0)
+///     .specific_heat(385.0)
+///     .thermal_conductivity(0.0, 0.0, 401.0)
+///     .build();
+/// assert_eq!(from_constant, from_coefficients);
+/// ```
+pub struct MaterialBuilder {
+    material: Material,
+}
+
+impl MaterialBuilder {
+    /// Set the density in kg/m^3
+    pub fn density(mut self, density: f32) -> Self {
+        self.material.density = density;
+        return self;
+    }
+
+    /// Set a constant specific heat in J / K * kg
+    pub fn specific_heat(mut self, specific_heat: f32) -> Self {
+        self.material.specific_heat = (0.0, 0.0, specific_heat);
+        return self;
+    }
+
+    /// Set the coefficients (a, b, c) of the specific heat C in J / K * kg. Given as C = a*t^2 + b*t + c
+    pub fn specific_heat_coefficients(mut self, a: f32, b: f32, c: f32) -> Self {
+        self.material.specific_heat = (a, b, c);
+        return self;
+    }
+
+    /// Set the coefficients (a, b, c) of the thermal conductivity C in W / m K along every axis. Given as C = a*t^2 + b*t + c
+    pub fn thermal_conductivity(mut self, a: f32, b: f32, c: f32) -> Self {
+        self.material.thermal_conductivity = [(a, b, c); 3];
+        return self;
+    }
+
+    /// Set the coefficients (a, b, c) of the thermal conductivity C in W / m K along a single axis. Given as C = a*t^2 + b*t + c
+    pub fn thermal_conductivity_axis(mut self, axis: Axis, a: f32, b: f32, c: f32) -> Self {
+        self.material.thermal_conductivity[axis as usize] = (a, b, c);
+        return self;
+    }
+
+    /// Set a constant thermal conductivity in W / m K along every axis
+    pub fn constant_conductivity(self, conductivity: f32) -> Self {
+        self.thermal_conductivity(0.0, 0.0, conductivity)
+    }
+
+    /// Set the linear thermal expansion coefficient in 1 / K, and the temperature in kelvin at
+    /// which the material has its nominal density
+    pub fn thermal_expansion(mut self, expansion_coeff: f32, reference_temp: f32) -> Self {
+        self.material.expansion_coeff = expansion_coeff;
+        self.material.reference_temp = reference_temp;
+        return self;
+    }
+
+    /// Set the melting point in kelvin and the latent heat of fusion in J / kg
+    pub fn melting(mut self, melting_point: f32, latent_heat: f32) -> Self {
+        self.material.melting_point = melting_point;
+        self.material.latent_heat_fusion = latent_heat;
+        return self;
+    }
+
+    /// Set the boiling point in kelvin and the latent heat of vaporization in J / kg
+    pub fn boiling(mut self, boiling_point: f32, latent_heat: f32) -> Self {
+        self.material.boiling_point = boiling_point;
+        self.material.latent_heat_vaporization = latent_heat;
+        return self;
+    }
+
+    /// Set the surface emissivity, from 0.0 to 1.0
+    pub fn emissivity(mut self, emissivity: f32) -> Self {
+        self.material.emissivity = emissivity;
+        return self;
+    }
+
+    /// Set the convective heat transfer coefficient in W / m^2 K
+    pub fn convective_coefficient(mut self, coefficient: f32) -> Self {
+        self.material.convective_coefficient = coefficient;
+        return self;
+    }
+
+    /// Build the material
+    pub fn build(self) -> Material {
+        self.material
+    }
+}
+
+// Materials are compared and hashed on the exact bits of their fields, so that hashing stays
+// consistent with equality when materials are used as map keys
+impl Material {
+    fn field_bits(&self) -> [u32; 21] {
+        let [(kxa, kxb, kxc), (kya, kyb, kyc), (kza, kzb, kzc)] = self.thermal_conductivity;
+        [
+            self.density.to_bits(),
+            self.specific_heat.0.to_bits(),
+            self.specific_heat.1.to_bits(),
+            self.specific_heat.2.to_bits(),
+            kxa.to_bits(),
+            kxb.to_bits(),
+            kxc.to_bits(),
+            kya.to_bits(),
+            kyb.to_bits(),
+            kyc.to_bits(),
+            kza.to_bits(),
+            kzb.to_bits(),
+            kzc.to_bits(),
+            self.expansion_coeff.to_bits(),
+            self.reference_temp.to_bits(),
+            self.melting_point.to_bits(),
+            self.boiling_point.to_bits(),
+            self.latent_heat_fusion.to_bits(),
+            self.latent_heat_vaporization.to_bits(),
+            self.emissivity.to_bits(),
+            self.convective_coefficient.to_bits(),
+        ]
+    }
+}
+
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        self.field_bits() == other.field_bits()
     }
 }
 
 impl Hash for Material {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        state.write_i64((self.density * 10.0) as i64);
-        state.write_i64((self.specific_heat * 10.0) as i64);
-        state.write_i64((self.thermal_conductivity.0 * 10.0) as i64);
-        state.write_i64((self.thermal_conductivity.1 * 10.0) as i64);
-        state.write_i64((self.thermal_conductivity.2 * 10.0) as i64);
+        self.field_bits().hash(state);
     }
 }
 
 impl Eq for Material {}
+
+/// Possible errors when loading a material library
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum LibraryError {
+    /// The library file could not be read
+    Io(std::io::Error),
+    /// The library file is not a valid material library, including missing material fields and
+    /// coefficient lists of the wrong length
+    Parse(toml::de::Error),
+}
+
+/// Load a library of named materials from a TOML file
+///
+/// See [parse_library] for the expected format.
+#[cfg(feature = "serde")]
+pub fn load_library(
+    path: &std::path::Path,
+) -> Result<std::collections::HashMap<String, Material>, LibraryError> {
+    let source = std::fs::read_to_string(path).map_err(LibraryError::Io)?;
+    return parse_library(&source);
+}
+
+/// Parse a library of named materials from a TOML string
+///
+/// Each material is a table keyed by its name. `density`, `specific_heat` and
+/// `thermal_conductivity` are required, the remaining [Material] fields are optional and default
+/// to no thermal expansion and no phase changes. Coefficients may be given as a list of three
+/// values `[a, b, c]`, or as a single value for a constant. `thermal_conductivity` may also be
+/// given as a list of three coefficient lists, one for each [Axis].
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::material::parse_library;
+/// let library = parse_library(r#"
+///     [copper]
+///     density = 8960
+///     specific_heat = 385
+///     thermal_conductivity = [0.0, 0.0, 401.0]
+/// "#).unwrap();
+/// assert_eq!(library["copper"].density, 8960.0);
+/// assert_eq!(library["copper"].specific_heat, (0.0, 0.0, 385.0));
+///
+/// assert!(parse_library("[copper]\ndensity = 8960").is_err());
+/// ```
+#[cfg(feature = "serde")]
+pub fn parse_library(
+    source: &str,
+) -> Result<std::collections::HashMap<String, Material>, LibraryError> {
+    return toml::from_str(source).map_err(LibraryError::Parse);
+}
+
+#[cfg(feature = "serde")]
+fn default_reference_temp() -> f32 {
+    BLANK.reference_temp
+}
+
+#[cfg(feature = "serde")]
+fn default_phase_point() -> f32 {
+    f32::INFINITY
+}
+
+/// Deserialization of polynomial coefficients from either a constant or a list of three values
+#[cfg(feature = "serde")]
+mod coefficients {
+    use serde::de::{Deserialize, Deserializer, Error, SeqAccess, Visitor};
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<(f32, f32, f32), D::Error> {
+        // Binary formats are not self describing, and always hold the full coefficients
+        if !deserializer.is_human_readable() {
+            return <(f32, f32, f32)>::deserialize(deserializer);
+        }
+        match Entry::deserialize(deserializer)? {
+            Entry::Value(v) => Ok((0.0, 0.0, v)),
+            Entry::Coefficients(c) => Ok(c),
+        }
+    }
+
+    /// Deserialize coefficients for each axis, where a single set of coefficients is isotropic
+    pub fn deserialize_axes<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[(f32, f32, f32); 3], D::Error> {
+        if !deserializer.is_human_readable() {
+            return <[(f32, f32, f32); 3]>::deserialize(deserializer);
+        }
+        deserializer.deserialize_any(AxesVisitor)
+    }
+
+    /// Either a single value or a full set of coefficients
+    enum Entry {
+        Value(f32),
+        Coefficients((f32, f32, f32)),
+    }
+
+    impl<'de> Deserialize<'de> for Entry {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(EntryVisitor)
+        }
+    }
+
+    struct EntryVisitor;
+
+    impl<'de> Visitor<'de> for EntryVisitor {
+        type Value = Entry;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a number or a list of 3 coefficients [a, b, c]")
+        }
+
+        fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+            Ok(Entry::Value(v as f32))
+        }
+
+        fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(Entry::Value(v as f32))
+        }
+
+        fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Entry::Value(v as f32))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut values: Vec<f32> = Vec::new();
+            while let Some(v) = seq.next_element::<f32>()? {
+                values.push(v);
+            }
+            match values[..] {
+                [a, b, c] => Ok(Entry::Coefficients((a, b, c))),
+                _ => Err(A::Error::invalid_length(values.len(), &self)),
+            }
+        }
+    }
+
+    struct AxesVisitor;
+
+    impl<'de> Visitor<'de> for AxesVisitor {
+        type Value = [(f32, f32, f32); 3];
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str(
+                "a number, a list of 3 coefficients [a, b, c], or a list of 3 coefficient lists",
+            )
+        }
+
+        fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+            Ok([(0.0, 0.0, v as f32); 3])
+        }
+
+        fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok([(0.0, 0.0, v as f32); 3])
+        }
+
+        fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok([(0.0, 0.0, v as f32); 3])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut entries: Vec<Entry> = Vec::new();
+            while let Some(entry) = seq.next_element::<Entry>()? {
+                entries.push(entry);
+            }
+            match entries[..] {
+                [Entry::Value(a), Entry::Value(b), Entry::Value(c)] => Ok([(a, b, c); 3]),
+                [
+                    Entry::Coefficients(x),
+                    Entry::Coefficients(y),
+                    Entry::Coefficients(z),
+                ] => Ok([x, y, z]),
+                _ => Err(A::Error::invalid_length(entries.len(), &self)),
+            }
+        }
+    }
+}
@@ -1,99 +1,545 @@
-use crate::world::{SimState, SimWorld};
+use crate::{
+    material::{Axis, Material},
+    world::{Float, SimState, SimWorld},
+};
 
-use super::{SimError, SimRunner};
+use super::SimRunner;
 
-static CELL_KERLEL: [(i8, i8, i8); 6] = [
-    (1, 0, 0),
-    (0, 1, 0),
-    (0, 0, 1),
-    (-1, 0, 0),
-    (0, -1, 0),
-    (0, 0, -1),
+pub(crate) static CELL_KERLEL: [(i8, i8, i8, Axis); 6] = [
+    (1, 0, 0, Axis::X),
+    (0, 1, 0, Axis::Y),
+    (0, 0, 1, Axis::Z),
+    (-1, 0, 0, Axis::X),
+    (0, -1, 0, Axis::Y),
+    (0, 0, -1, Axis::Z),
 ];
 
+/// The set of neighbors each cell exchanges heat with, as the offset to each neighbor and a
+/// weight scaling the conductance to it
+///
+/// Heat only flows symmetrically, conserving energy, if every offset is matched by the opposite
+/// offset with the same weight.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::{CPUSimRunner, Stencil}}};
+/// // A 4 neighbor kernel for planar worlds
+/// let planar = Stencil::new(vec![
+///     ((1, 0, 0), 1.0),
+///     ((-1, 0, 0), 1.0),
+///     ((0, 1, 0), 1.0),
+///     ((0, -1, 0), 1.0),
+/// ]);
+/// assert_eq!(planar.get_offsets().len(), 4);
+/// assert_eq!(Stencil::moore_26().get_offsets().len(), 26);
+///
+/// let plate = AABBVolume::new(0.0, 0.0, 0.0, 0.03, 0.02, 0.01);
+/// let world = SimWorldBuilder::new_2d(0.03, 0.02)
+///     .with_material(material::WATER, Box::new(plate.clone()))
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.02, 0.01)))
+///     .build(0.01);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &plate).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01)).unwrap();
+/// let state = state.to_precision::<f64>();
+///
+/// // In a planar world the 4 neighbor kernel matches the 6 face neighbors
+/// let planar_result = CPUSimRunner { stencil: planar, ..Default::default() }.advance_step(&world, state.clone(), 0.1);
+/// let face_result = CPUSimRunner { stencil: Stencil::faces_6(), ..Default::default() }.advance_step(&world, state, 0.1);
+/// for (planar, faces) in planar_result.get_energies().iter().zip(face_result.get_energies()) {
+///     assert!((planar - faces).abs() < 1e-9);
+/// }
+///
+/// // The face stencil gives exactly these results
+/// assert_eq!(
+///     face_result.get_energies(),
+///     &[
+///         1340.0415118561355,
+///         1534.1225191430522,
+///         1534.0001220703125,
+///         1074.5561838801837,
+///         1534.0001220346912,
+///         1534.0001220703125,
+///     ]
+/// );
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stencil {
+    offsets: Vec<((i8, i8, i8), f32)>,
+}
+
+impl Stencil {
+    /// Create a new stencil from the offset to each neighbor and the weight of its conductance
+    ///
+    /// Panics if an offset is `(0, 0, 0)`, or if any offset is not matched by the opposite offset
+    /// with the same weight, as heat would not flow symmetrically.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::runner::cpu::Stencil;
+    /// let line = Stencil::new(vec![((1, 0, 0), 1.0), ((-1, 0, 0), 1.0)]);
+    /// assert_eq!(line.get_offsets().len(), 2);
+    ///
+    /// let invalid = [
+    ///     vec![((0, 0, 0), 1.0)],
+    ///     vec![((1, 0, 0), 1.0)],
+    ///     vec![((1, 0, 0), 1.0), ((-1, 0, 0), 0.5)],
+    /// ];
+    /// for offsets in invalid {
+    ///     assert!(std::panic::catch_unwind(|| Stencil::new(offsets)).is_err());
+    /// }
+    /// ```
+    pub fn new(offsets: Vec<((i8, i8, i8), f32)>) -> Stencil {
+        for ((dx, dy, dz), weight) in offsets.iter() {
+            assert!(
+                (*dx, *dy, *dz) != (0, 0, 0),
+                "A stencil can not offset a cell to itself"
+            );
+            let opposite = offsets.iter().find(|((x, y, z), _)| {
+                // -128 has no opposite within an i8
+                return [(x, dx), (y, dy), (z, dz)]
+                    .iter()
+                    .all(|(a, b)| b.checked_neg() == Some(**a));
+            });
+            assert!(
+                opposite.is_some_and(|(_, opposite_weight)| opposite_weight == weight),
+                "Stencil offset ({dx}, {dy}, {dz}) must be matched by its opposite with the same weight"
+            );
+        }
+        Stencil { offsets }
+    }
+
+    /// The 6 neighbors sharing a face with the cell, each with a weight of 1
+    pub fn faces_6() -> Stencil {
+        return Stencil::new(
+            CELL_KERLEL
+                .iter()
+                .map(|(dx, dy, dz, _)| ((*dx, *dy, *dz), 1.0))
+                .collect(),
+        );
+    }
+
+    /// All 26 neighbors sharing a face, edge or corner with the cell
+    ///
+    /// Diagonal neighbors are weighted by `1/√2` for edges and `1/√3` for corners, and all
+    /// conductances are scaled so that a uniform gradient diffuses at the same rate as with
+    /// [Stencil::faces_6]. This spreads heat more evenly in every direction, at a higher cost per
+    /// step. The scaling assumes cubic cells.
+    pub fn moore_26() -> Stencil {
+        return Stencil::new(
+            (0..27)
+                .filter(|i| *i != 13)
+                .map(|i| {
+                    (
+                        (i % 3 - 1) as i8,
+                        ((i / 3) % 3 - 1) as i8,
+                        (i / 9 - 1) as i8,
+                    )
+                })
+                .map(|(dx, dy, dz)| {
+                    let axes_crossed = (dx != 0) as u8 + (dy != 0) as u8 + (dz != 0) as u8;
+                    ((dx, dy, dz), MOORE_SCALE / (axes_crossed as f32).sqrt())
+                })
+                .collect(),
+        );
+    }
+
+    /// Get the offset to each neighbor and the weight of its conductance
+    pub fn get_offsets(&self) -> &[((i8, i8, i8), f32)] {
+        self.offsets.as_slice()
+    }
+}
+
+impl Default for Stencil {
+    /// The 6 face neighbors, see [Stencil::faces_6]
+    fn default() -> Self {
+        return Stencil::faces_6();
+    }
+}
+
+/// How the conductivities of two neighboring cells are combined into the effective conductivity
+/// of the gap between them
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::{CPUSimRunner, InterfaceAveraging}}};
+/// assert_eq!(InterfaceAveraging::Harmonic.combine(1.0, 4.0), 1.6);
+/// assert_eq!(InterfaceAveraging::Geometric.combine(1.0, 4.0), 2.0);
+/// assert_eq!(InterfaceAveraging::Arithmetic.combine(1.0, 4.0), 2.5);
+///
+/// // Hot copper against cold water, a sharp contrast in conductivity
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.01, 0.01);
+/// let world = SimWorldBuilder::new(0.02, 0.01, 0.01)
+///     .with_material(material::WATER, Box::new(everything.clone()))
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01)))
+///     .build(0.01);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+/// state = world.set_sim_state_temperature(state, 310.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01)).unwrap();
+///
+/// let flux = |averaging: InterfaceAveraging| {
+///     let runner = CPUSimRunner { averaging, ..Default::default() };
+///     let result = runner.advance_step(&world, state.clone(), 0.01);
+///     return result.get_energies()[1] - state.get_energies()[1];
+/// };
+/// // The water limits the flow through the harmonic mean, but not the arithmetic mean
+/// let (harmonic, arithmetic) = (flux(InterfaceAveraging::Harmonic), flux(InterfaceAveraging::Arithmetic));
+/// assert!(harmonic > 0.0 && arithmetic > 100.0 * harmonic);
+/// assert_eq!(harmonic, flux(InterfaceAveraging::default()));
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum InterfaceAveraging {
+    /// The mean of the two conductivities
+    Arithmetic,
+    /// The harmonic mean of the two conductivities. The two halves of the gap between cells
+    /// conduct in series, so this is the physically correct choice
+    #[default]
+    Harmonic,
+    /// The square root of the product of the two conductivities
+    Geometric,
+}
+
+impl InterfaceAveraging {
+    /// Combine the conductivities of two cells in W / (m K) into the effective conductivity
+    /// between them. The result is 0 if either conductivity is 0, except for
+    /// [InterfaceAveraging::Arithmetic]
+    pub fn combine(&self, a: f32, b: f32) -> f32 {
+        return match self {
+            InterfaceAveraging::Arithmetic => (a + b) / 2.0,
+            InterfaceAveraging::Harmonic => {
+                let sum = a + b;
+                if sum == 0.0 { 0.0 } else { 2.0 * a * b / sum }
+            }
+            InterfaceAveraging::Geometric => (a * b).sqrt(),
+        };
+    }
+}
+
+/// Scale applied to Moore stencil conductances, so that the sum of conductance times squared
+/// offset along each axis matches the face stencil: `2 / (2 + 8/(2√2) + 8/(3√3))`
+const MOORE_SCALE: f32 =
+    2.0 / (2.0 + 4.0 * std::f32::consts::FRAC_1_SQRT_2 + 8.0 / (3.0 * 1.732_050_8));
+
 /// Simulation Runner that uses a single CPU thread to execute
-pub struct CPUSimRunner {}
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+/// let world = SimWorldBuilder::new(4.0, 1.0, 1.0)
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0)))
+///     .build(1.0);
+/// let mut state = world.get_blank_sim_state();
+/// state = world.set_sim_state_temperature(state, 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0)).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0)).unwrap();
+///
+/// let runner = CPUSimRunner::default();
+/// let result = futures::executor::block_on(runner.advance_simulation(&world, &state, 100.0, 1.0)).unwrap();
+///
+/// // Heat has flowed from the hot half to the cold half, without any being lost
+/// assert!(world.sample_voxel_temperature(&result, 3, 0, 0).unwrap() > 300.0);
+/// let (before, after) = (world.total_energy(&state), world.total_energy(&result));
+/// assert!(((after - before) / before).abs() < 1e-6);
+/// ```
+///
+/// The [Stencil::moore_26] stencil spreads a hot spot more evenly than the default
+/// [Stencil::faces_6] stencil
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::{CPUSimRunner, Stencil}}};
+/// # use futures::executor::block_on;
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 13.0, 13.0, 1.0);
+/// let world = SimWorldBuilder::new(13.0, 13.0, 1.0)
+///     .with_material(material::COPPER, Box::new(everything.clone()))
+///     .build(1.0);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+/// state = world.set_sim_state_temperature(state, 1000.0, &AABBVolume::new(6.0, 6.0, 0.0, 7.0, 7.0, 1.0)).unwrap();
+///
+/// // Compare two cells 5 cells from the hot spot, one along an axis and one off axis
+/// let anisotropy = |stencil: Stencil| {
+///     let runner = CPUSimRunner { stencil, ..Default::default() };
+///     let result = block_on(runner.advance_simulation(&world, &state, 20_000.0, 500.0)).unwrap();
+///     let along_axis = world.sample_voxel_temperature(&result, 11, 6, 0).unwrap() - 300.0;
+///     let off_axis = world.sample_voxel_temperature(&result, 9, 10, 0).unwrap() - 300.0;
+///     return (along_axis - off_axis).abs() / along_axis.max(off_axis);
+/// };
+/// assert!(anisotropy(Stencil::moore_26()) < anisotropy(Stencil::faces_6()));
+/// ```
+///
+/// Anisotropic materials conduct along each axis with that axis' conductivity, so a hot spot
+/// spreads into an ellipse rather than a circle
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+/// # use futures::executor::block_on;
+/// // A sheet in the x z plane which conducts ten times better along x than along z
+/// let layered = Material {
+///     thermal_conductivity: [(0.0, 0.0, 400.0), (0.0, 0.0, 400.0), (0.0, 0.0, 40.0)],
+///     ..material::COPPER
+/// };
+/// let spread = |material: Material| {
+///     let everything = AABBVolume::new(0.0, 0.0, 0.0, 9.0, 1.0, 9.0);
+///     let world = SimWorldBuilder::new(9.0, 1.0, 9.0)
+///         .with_material(material, Box::new(everything.clone()))
+///         .build(1.0);
+///     let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+///     state = world.set_sim_state_temperature(state, 1000.0, &AABBVolume::new(4.0, 0.0, 4.0, 5.0, 1.0, 5.0)).unwrap();
+///     let result = block_on(CPUSimRunner::default().advance_simulation(&world, &state, 20_000.0, 500.0)).unwrap();
+///     let along_x = world.sample_voxel_temperature(&result, 7, 0, 4).unwrap() - 300.0;
+///     let along_z = world.sample_voxel_temperature(&result, 4, 0, 7).unwrap() - 300.0;
+///     return along_x / along_z;
+/// };
+/// assert!(spread(layered) > 2.0);
+/// assert!((spread(material::COPPER) - 1.0).abs() < 1e-4);
+/// ```
+///
+/// Heat conducted between cells scales with the area of the face between them over the
+/// distance between their centers
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+/// let conductor = Material { thermal_conductivity: [(0.0, 0.0, 400.0); 3], ..material::COPPER };
+/// // Energy flowing in one short step between two cells along x, one hotter than the other
+/// let flow = |(dx, dy, dz): (f64, f64, f64)| {
+///     let everything = AABBVolume::new(0.0, 0.0, 0.0, 2.0 * dx, dy, dz);
+///     let world = SimWorldBuilder::new(2.0 * dx, dy, dz)
+///         .with_material(conductor, Box::new(everything.clone()))
+///         .build_with_cell_size(dx, dy, dz);
+///     let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+///     state = world.set_sim_state_temperature(state, 301.0, &AABBVolume::new(0.0, 0.0, 0.0, dx, dy, dz)).unwrap();
+///     let result = CPUSimRunner::default().advance_step(&world, state.clone(), 0.1);
+///     return (result.get_energies()[1] - state.get_energies()[1]) as f64;
+/// };
+///
+/// let base = flow((0.01, 0.01, 0.01));
+/// // Doubling every side quadruples the face area and doubles the distance
+/// assert!((flow((0.02, 0.02, 0.02)) / base - 2.0).abs() < 1e-2);
+/// // Doubling only the length along the flow keeps the face area and doubles the distance
+/// assert!((flow((0.02, 0.01, 0.01)) / base - 0.5).abs() < 1e-2);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct CPUSimRunner {
+    /// The set of neighbors each cell exchanges heat with
+    pub stencil: Stencil,
+    /// How the conductivities of neighboring cells are combined
+    pub averaging: InterfaceAveraging,
+}
 
-impl SimRunner for CPUSimRunner {
-    /// Advace the given simulation state in the given world by a given ammount of total time with
-    /// a given timestep
-    async fn advance_simulation(
+impl<E: Float> SimRunner<E> for CPUSimRunner {
+    /// Advance the given simulation state in the given world by a single timestep
+    fn advance_step(
         &self,
         world: &SimWorld,
-        current_state: &SimState,
-        advace_time: f64,
-        timestep: f64,
-    ) -> Result<SimState, SimError> {
-        if !world.is_state_valid(current_state) {
-            return Err(SimError::SimStateInvalid);
-        }
-        let mut active_state = current_state.clone();
-        let mut remaining_time = advace_time;
-        while remaining_time > 0.0 {
-            active_state =
-                advance_world_state(world, active_state, timestep.min(remaining_time) as f32);
-            remaining_time -= timestep;
-        }
-        return Ok(active_state);
+        mut current_state: SimState<E>,
+        deltatime: f32,
+    ) -> SimState<E> {
+        let temperatures = world
+            .precise_temperature_field(&current_state)
+            .expect("Runners must only be given valid states");
+        let energy_deltas: Vec<E> = (0..temperatures.len())
+            .map(|cell_index| {
+                cell_energy_delta(
+                    world,
+                    &temperatures,
+                    cell_index,
+                    deltatime,
+                    &self.stencil,
+                    self.averaging,
+                )
+            })
+            .collect();
+
+        current_state.apply_deltas(energy_deltas);
+        world.apply_fixed_temperatures(&mut current_state);
+        return current_state;
     }
 }
 
-fn advance_world_state(world: &SimWorld, mut current_state: SimState, deltatime: f32) -> SimState {
-    // Create energy delta vector
-    let mut energy_deltas: Vec<f32> = Vec::new();
-    energy_deltas.resize(
-        world.get_x_size() * world.get_y_size() * world.get_z_size(),
-        0.0,
-    );
+/// Calculate the change in energy of a single cell over a timestep, given the temperature of
+/// every cell in the world at the start of the timestep
+///
+/// Each cell's change only depends on the starting temperatures, so cells can be calculated in
+/// any order, or in parallel, with identical results. Temperature differences and the flows
+/// they drive are calculated at the precision of the state, material properties are single
+/// precision.
+pub(crate) fn cell_energy_delta<E: Float>(
+    world: &SimWorld,
+    temperatures: &[E],
+    cell_index: usize,
+    deltatime: f32,
+    stencil: &Stencil,
+    averaging: InterfaceAveraging,
+) -> E {
+    let cell_volume = world.get_cell_volume();
+    let cell_temperature = temperatures[cell_index];
 
-    let current_energies = current_state.get_energies();
+    // Energy flow into this cell from the neighbor at the given offset, scaled by a weight
+    let neighbor_flow = |offset: (i8, i8, i8), weight: f32| -> E {
+        let (neighbor_index, effective_thermal_con, face_area, distance) =
+            match neighbor_conductance(world, temperatures, cell_index, offset, averaging) {
+                Some(c) => c,
+                None => return E::default(),
+            };
+
+        // Calculate energy flow into this cell. The neighbor calculates the same flow
+        // with the temperature difference negated, so energy is conserved
+        let heat_delta = temperatures[neighbor_index] - cell_temperature;
+        return conductive_energy(
+            effective_thermal_con,
+            heat_delta,
+            face_area,
+            distance,
+            deltatime,
+        ) * E::from_f32(weight);
+    };
+
+    let mut cell_energy_delta: E = stencil
+        .get_offsets()
+        .iter()
+        .map(|(offset, weight)| neighbor_flow(*offset, *weight))
+        .sum();
+
+    // Add the energy lost by convection to the surrounding fluid
+    let ambient_flow = E::from_f32(convective_conductance(world, cell_index))
+        * (E::from_f32(world.get_ambient_temperature()) - cell_temperature);
+    cell_energy_delta += ambient_flow * E::from_f32(deltatime);
+
+    // Add the energy from any heat source in this cell
+    cell_energy_delta += E::from_f32(world.get_heat_source(cell_index))
+        * E::from_f64(cell_volume)
+        * E::from_f32(deltatime);
+
+    return cell_energy_delta;
+}
+
+/// Get the energy in joules conducted into a cell across the gap to a neighbor over a timestep,
+/// Fourier's law `k * A * ΔT / d * dt`
+///
+/// - `effective_conductivity`: `k`, the conductivity across the gap in W / (m K)
+/// - `temperature_difference`: `ΔT`, the neighbor's temperature minus the cell's in K
+/// - `face_area`: `A`, the area in m^2 heat flows through
+/// - `distance`: `d`, the distance in m between the cell centers
+/// - `deltatime`: `dt`, the timestep in s
+pub(crate) fn conductive_energy<E: Float>(
+    effective_conductivity: f32,
+    temperature_difference: E,
+    face_area: f64,
+    distance: f64,
+    deltatime: f32,
+) -> E {
+    return temperature_difference
+        * E::from_f32(effective_conductivity)
+        * E::from_f32(deltatime)
+        * E::from_f64(face_area / distance);
+}
+
+/// Get the index of the neighbor at an offset from a cell, the effective conductivity between
+/// the two cells in W / (m K), the area in m^2 heat flows through between them, and the
+/// distance in m between their centers. The conductance between the cells in W / K is the
+/// conductivity times the area over the distance.
+///
+/// Neighbors across a face share the face's area. Diagonal neighbors have no shared face, so
+/// they are given the cell volume over their distance as an effective area, which reduces to
+/// the face area for face neighbors.
+///
+/// Returns None if the neighbor is outside the world. The result is the same when calculated
+/// from either cell, so heat flow between the cells is symmetric.
+pub(crate) fn neighbor_conductance<E: Float>(
+    world: &SimWorld,
+    temperatures: &[E],
+    cell_index: usize,
+    (dx, dy, dz): (i8, i8, i8),
+    averaging: InterfaceAveraging,
+) -> Option<(usize, f32, f64, f64)> {
     let materials = world.get_materials();
     let mat_map = world.get_material_map();
-    let cell_size = world.get_cell_volume();
-    let cell_dist = world.get_cell_length();
-
-    for x in 0..world.get_x_size() {
-        for y in 0..world.get_y_size() {
-            for z in 0..world.get_z_size() {
-                let cell_index = world
-                    .get_pos_index(x, y, z)
-                    .expect("We know we are iterating over positions in the world");
-                let cell_material = mat_map[materials[cell_index] as usize];
-                let cell_mass = cell_material.density * cell_size;
-                let cell_temperature =
-                    current_energies[cell_index] / (cell_material.specific_heat * cell_mass);
-                let cell_thermal_conductivity =
-                    cell_material.get_thermal_conductivity(cell_temperature);
-                let cell_energy_delta = energy_deltas
-                    .get_mut(cell_index)
-                    .expect("Energy delta list is known to be the same size as energy list");
-
-                for neighbor_index in CELL_KERLEL.iter().filter_map(|(dx, dy, dz)| {
-                    world.get_ipos_index(
-                        x as i128 + *dx as i128,
-                        y as i128 + *dy as i128,
-                        z as i128 + *dz as i128,
-                    )
-                }) {
-                    let neighbor_material = mat_map[materials[neighbor_index] as usize];
-                    let neighbor_mass = neighbor_material.density * cell_size;
-                    let neighbor_temperature = current_energies[neighbor_index]
-                        / (neighbor_material.specific_heat * neighbor_mass);
-                    let neighbor_thermal_conductivity =
-                        neighbor_material.get_thermal_conductivity(neighbor_temperature);
-
-                    let effective_thermal_con =
-                        (cell_thermal_conductivity * neighbor_thermal_conductivity) / 2.0;
-
-                    // Calculate energy flow and apply to both energy deltas
-                    let heat_delta = neighbor_temperature - cell_temperature;
-                    *cell_energy_delta +=
-                        heat_delta * effective_thermal_con * deltatime * cell_dist;
-                }
-            }
-        }
+    let cell_size = [Axis::X, Axis::Y, Axis::Z].map(|axis| world.get_cell_length(axis));
+
+    let (x, y, z) = world
+        .get_index_pos(cell_index)
+        .expect("We know we are iterating over positions in the world");
+    let neighbor_index = world.get_ipos_index(
+        x as i128 + dx as i128,
+        y as i128 + dy as i128,
+        z as i128 + dz as i128,
+    )?;
+    let cell_material = mat_map[materials[cell_index] as usize];
+    let neighbor_material = mat_map[materials[neighbor_index] as usize];
+    let offset = [dx, dy, dz];
+    let cell_thermal_conductivity = directional_conductivity(
+        &cell_material,
+        temperatures[cell_index].to_f32(),
+        offset,
+        cell_size,
+    );
+    let neighbor_thermal_conductivity = directional_conductivity(
+        &neighbor_material,
+        temperatures[neighbor_index].to_f32(),
+        offset,
+        cell_size,
+    );
+
+    let effective_thermal_con =
+        averaging.combine(cell_thermal_conductivity, neighbor_thermal_conductivity);
+
+    let distance = (0..3)
+        .map(|i| (offset[i] as f64 * cell_size[i]).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    let face_area = world.get_cell_volume() / distance;
+
+    return Some((neighbor_index, effective_thermal_con, face_area, distance));
+}
+
+/// Get the conductance in W / K between a cell and the fluid surrounding the world, through
+/// every face the cell shares with a cell of the world's default material
+pub(crate) fn convective_conductance(world: &SimWorld, cell_index: usize) -> f32 {
+    let materials = world.get_materials();
+    let material = world.get_material_map()[materials[cell_index] as usize];
+    let coefficient = material.convective_coefficient;
+    if materials[cell_index] == 0 || coefficient == 0.0 || !material.has_heat_capacity() {
+        return 0.0;
     }
 
-    current_state.apply_deltas(energy_deltas.into_iter());
-    return current_state;
+    let (x, y, z) = world
+        .get_index_pos(cell_index)
+        .expect("We know we are iterating over positions in the world");
+    let cell_volume = world.get_cell_volume();
+    return CELL_KERLEL
+        .iter()
+        .filter_map(|(dx, dy, dz, axis)| {
+            let neighbor = world.get_ipos_index(
+                x as i128 + *dx as i128,
+                y as i128 + *dy as i128,
+                z as i128 + *dz as i128,
+            )?;
+            (materials[neighbor] == 0)
+                .then(|| coefficient * (cell_volume / world.get_cell_length(*axis)) as f32)
+        })
+        .sum();
+}
+
+/// Get the conductivity of a material along the direction of a cell offset
+///
+/// Along a diagonal, each axis' conductivity contributes in proportion to the squared length of
+/// the offset along that axis. Materials without heat capacity do not conduct.
+fn directional_conductivity(
+    material: &Material,
+    temperature: f32,
+    offset: [i8; 3],
+    cell_size: [f64; 3],
+) -> f32 {
+    if !material.has_heat_capacity() {
+        return 0.0;
+    }
+    let axes = [Axis::X, Axis::Y, Axis::Z];
+    if offset.iter().filter(|o| **o != 0).count() == 1 {
+        let axis = (0..3)
+            .find(|i| offset[*i] != 0)
+            .expect("One axis is crossed");
+        return material.get_thermal_conductivity_axis(temperature, axes[axis]);
+    }
+    let (mut weighted_sum, mut total) = (0.0, 0.0);
+    for (i, axis) in axes.into_iter().enumerate() {
+        if offset[i] != 0 {
+            let length = (cell_size[i] as f32).powi(2);
+            weighted_sum += material.get_thermal_conductivity_axis(temperature, axis) * length;
+            total += length;
+        }
+    }
+    return weighted_sum / total;
 }
@@ -0,0 +1,260 @@
+use wgpu::util::DeviceExt;
+
+use crate::{
+    material::Axis,
+    world::{Float, SimState, SimWorld, WorldBoundary},
+};
+
+use super::SimRunner;
+
+/// Cells handled by each workgroup of the compute shader
+const WORKGROUP_SIZE: u32 = 64;
+/// Largest number of workgroups which can be dispatched along one dimension
+const MAX_WORKGROUPS: u32 = 65535;
+
+/// Possible errors when creating a [GpuSimRunner]
+#[derive(Debug)]
+pub enum GpuError {
+    /// No GPU adapter is available
+    NoAdapter(wgpu::RequestAdapterError),
+    /// A device could not be created on the GPU adapter
+    NoDevice(wgpu::RequestDeviceError),
+}
+
+/// Simulation Runner that calculates each timestep in a compute shader on the GPU, requires the
+/// `wgpu` feature
+///
+/// Temperatures are calculated on the CPU, then the energy flowing through each face of every
+/// cell is calculated on the GPU with the same face stencil as [super::cpu::CPUSimRunner], and
+/// read back to update the state. The GPU calculates each flow in single precision, so results
+/// match the CPU runner within floating point error rather than exactly, but the flows and heat
+/// sources of each cell are summed at the precision of the state.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner, gpu::GpuSimRunner}};
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.04, 0.03, 0.02);
+/// // Copper cools by convection where it borders the surrounding air
+/// let copper = Material { convective_coefficient: 25.0, ..material::COPPER };
+/// let world = SimWorldBuilder::new(0.04, 0.03, 0.02)
+///     .with_material(material::WATER, Box::new(AABBVolume::new(0.02, 0.0, 0.0, 0.04, 0.02, 0.02)))
+///     .with_material(copper, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.03, 0.02)))
+///     .build(0.01);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01)).unwrap();
+///
+/// // Skip the comparison on machines without a GPU
+/// let Ok(gpu) = GpuSimRunner::new() else {
+///     return;
+/// };
+/// let timestep = world.max_stable_timestep() as f32;
+/// let cpu_result = CPUSimRunner::default().advance_step(&world, state.clone(), timestep);
+/// let gpu_result = gpu.advance_step(&world, state, timestep);
+/// for (cpu, gpu) in cpu_result.get_energies().iter().zip(gpu_result.get_energies()) {
+///     assert!(((cpu - gpu) / cpu).abs() < 1e-5);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct GpuSimRunner {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuSimRunner {
+    /// Create a new GPU runner on the default GPU adapter, blocking until the device is ready
+    pub fn new() -> Result<GpuSimRunner, GpuError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .map_err(GpuError::NoAdapter)?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("thermal_rs"),
+            ..Default::default()
+        }))
+        .map_err(GpuError::NoDevice)?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("thermal_rs energy deltas"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("thermal_rs energy deltas"),
+            layout: None,
+            module: &shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        return Ok(GpuSimRunner {
+            device,
+            queue,
+            pipeline,
+        });
+    }
+
+    /// Calculate the energy flowing into every cell through each of its 6 faces over a timestep
+    /// on the GPU, in the order -x, +x, -y, +y, -z, +z
+    fn face_flows(&self, world: &SimWorld, temperatures: &[f32], deltatime: f32) -> Vec<f32> {
+        let cell_count = temperatures.len() as u32;
+        let workgroups = cell_count.div_ceil(WORKGROUP_SIZE);
+        let dispatch_x = workgroups.min(MAX_WORKGROUPS);
+        let dispatch_y = workgroups.div_ceil(dispatch_x);
+
+        let periodic = |axis: Axis| (world.get_boundary(axis) == WorldBoundary::Periodic) as u32;
+        let (cell_x, cell_y, cell_z) = world.get_cell_size();
+        let params: Vec<u8> = [
+            world.get_x_size() as u32,
+            world.get_y_size() as u32,
+            world.get_z_size() as u32,
+            cell_count,
+            periodic(Axis::X),
+            periodic(Axis::Y),
+            periodic(Axis::Z),
+            dispatch_x * WORKGROUP_SIZE,
+        ]
+        .iter()
+        .flat_map(|v| v.to_ne_bytes())
+        .chain(
+            [
+                cell_x as f32,
+                cell_y as f32,
+                cell_z as f32,
+                deltatime,
+                world.get_ambient_temperature(),
+            ]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes()),
+        )
+        .chain([0u8; 12])
+        .collect();
+        let material_table: Vec<u8> = world
+            .get_material_map()
+            .iter()
+            .flat_map(|m| {
+                // Materials without heat capacity do not conduct
+                if !m.has_heat_capacity() {
+                    return [[0.0; 4]; 4];
+                }
+                let [x, y, z] = m.thermal_conductivity.map(|(a, b, c)| [a, b, c, 0.0]);
+                [x, y, z, [m.convective_coefficient, 0.0, 0.0, 0.0]]
+            })
+            .flatten()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        let cell_materials: Vec<u8> = world
+            .get_materials()
+            .iter()
+            .flat_map(|m| (*m as u32).to_ne_bytes())
+            .collect();
+        let temperatures: Vec<u8> = temperatures.iter().flat_map(|t| t.to_ne_bytes()).collect();
+
+        let init = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents,
+                    usage,
+                })
+        };
+        let params = init("params", &params, wgpu::BufferUsages::UNIFORM);
+        let material_table = init(
+            "material table",
+            &material_table,
+            wgpu::BufferUsages::STORAGE,
+        );
+        let cell_materials = init(
+            "cell materials",
+            &cell_materials,
+            wgpu::BufferUsages::STORAGE,
+        );
+        let temperatures = init("temperatures", &temperatures, wgpu::BufferUsages::STORAGE);
+        let delta_size = cell_count as u64 * 6 * 4;
+        let deltas = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("face flows"),
+            size: delta_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("face flows readback"),
+            size: delta_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("thermal_rs energy deltas"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                &params,
+                &material_table,
+                &cell_materials,
+                &temperatures,
+                &deltas,
+            ]
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect::<Vec<_>>(),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(dispatch_x, dispatch_y, 1);
+        }
+        encoder.copy_buffer_to_buffer(&deltas, 0, &readback, 0, delta_size);
+        self.queue.submit([encoder.finish()]);
+
+        readback.map_async(wgpu::MapMode::Read, .., |result| {
+            result.expect("Energy deltas must be readable once the step completes");
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("The GPU device must complete the step");
+        let view = readback
+            .get_mapped_range(..)
+            .expect("Energy deltas are mapped once the step completes");
+        return view
+            .chunks_exact(4)
+            .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+    }
+}
+
+impl<E: Float> SimRunner<E> for GpuSimRunner {
+    /// Advance the given simulation state in the given world by a single timestep
+    fn advance_step(
+        &self,
+        world: &SimWorld,
+        mut current_state: SimState<E>,
+        deltatime: f32,
+    ) -> SimState<E> {
+        let temperatures = world
+            .temperature_field(&current_state)
+            .expect("Runners must only be given valid states");
+        if temperatures.is_empty() {
+            return current_state;
+        }
+
+        let volume = E::from_f64(world.get_cell_volume());
+        let dt = E::from_f32(deltatime);
+        let face_flows = self.face_flows(world, &temperatures, deltatime);
+        let energy_deltas = face_flows.chunks_exact(6).enumerate().map(|(i, flows)| {
+            let source = E::from_f32(world.get_heat_source(i)) * volume * dt;
+            return flows.iter().map(|f| E::from_f32(*f)).sum::<E>() + source;
+        });
+        current_state.apply_deltas(energy_deltas);
+        world.apply_fixed_temperatures(&mut current_state);
+        return current_state;
+    }
+}
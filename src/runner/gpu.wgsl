@@ -0,0 +1,111 @@
+// Calculates the energy flowing through each face of every cell over a timestep, heat flows
+// between the faces of neighboring cells as in the CPU runner's face stencil. The flows of each
+// cell are summed on the CPU, at the precision of the state
+
+struct Params {
+    size_x: u32,
+    size_y: u32,
+    size_z: u32,
+    cell_count: u32,
+    periodic_x: u32,
+    periodic_y: u32,
+    periodic_z: u32,
+    // Number of invocations in each row of the dispatch grid
+    row_stride: u32,
+    cell_x: f32,
+    cell_y: f32,
+    cell_z: f32,
+    deltatime: f32,
+    ambient_temperature: f32,
+    _padding_0: u32,
+    _padding_1: u32,
+    _padding_2: u32,
+}
+
+// Coefficients (a, b, c, unused) of the conductivity along each axis, and the convective
+// coefficient (h, unused, unused, unused) to the surrounding fluid
+struct MaterialCoefficients {
+    conductivity: array<vec4<f32>, 3>,
+    convection: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> material_table: array<MaterialCoefficients>;
+@group(0) @binding(2) var<storage, read> cell_materials: array<u32>;
+@group(0) @binding(3) var<storage, read> temperatures: array<f32>;
+// The energy flowing into each cell through each of its 6 faces, in the order -x, +x, -y, +y,
+// -z, +z
+@group(0) @binding(4) var<storage, read_write> face_flows: array<f32>;
+
+fn conductivity(material: u32, axis: u32, temperature: f32) -> f32 {
+    let c = material_table[material].conductivity[axis];
+    return c.x * temperature * temperature + c.y * temperature + c.z;
+}
+
+// Index of the neighbor one step along an axis, or -1 if it is outside an insulated face
+fn neighbor_index(position: vec3<i32>, axis: u32, step: i32) -> i32 {
+    let size = vec3<i32>(i32(params.size_x), i32(params.size_y), i32(params.size_z));
+    let periodic = vec3<u32>(params.periodic_x, params.periodic_y, params.periodic_z);
+    var neighbor = position;
+    neighbor[axis] = neighbor[axis] + step;
+    if neighbor[axis] < 0 || neighbor[axis] >= size[axis] {
+        if periodic[axis] == 0u {
+            return -1;
+        }
+        neighbor[axis] = (neighbor[axis] + size[axis]) % size[axis];
+    }
+    return neighbor.x + neighbor.y * size.x + neighbor.z * size.x * size.y;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x + id.y * params.row_stride;
+    if index >= params.cell_count {
+        return;
+    }
+    let position = vec3<i32>(
+        i32(index % params.size_x),
+        i32((index / params.size_x) % params.size_y),
+        i32(index / (params.size_x * params.size_y)),
+    );
+    let cell_size = vec3<f32>(params.cell_x, params.cell_y, params.cell_z);
+    let volume = cell_size.x * cell_size.y * cell_size.z;
+    let temperature = temperatures[index];
+    let material = cell_materials[index];
+
+    for (var axis = 0u; axis < 3u; axis = axis + 1u) {
+        for (var step = -1; step <= 1; step = step + 2) {
+            let face = index * 6u + axis * 2u + u32((step + 1) / 2);
+            var delta = 0.0;
+            face_flows[face] = 0.0;
+            let neighbor = neighbor_index(position, axis, step);
+            if neighbor < 0 {
+                continue;
+            }
+            let neighbor_material = cell_materials[neighbor];
+
+            // Faces bordering the default material lose heat by convection to the surrounding
+            // fluid at the ambient temperature
+            if material != 0u && neighbor_material == 0u {
+                let h = material_table[material].convection.x;
+                let area = volume / cell_size[axis];
+                delta = delta + h * area * (params.ambient_temperature - temperature) * params.deltatime;
+            }
+
+            let neighbor_temperature = temperatures[neighbor];
+            let cell_con = conductivity(material, axis, temperature);
+            let neighbor_con = conductivity(neighbor_material, axis, neighbor_temperature);
+
+            // Harmonic mean of the two conductivities, as the halves of the gap are in series
+            let con_sum = cell_con + neighbor_con;
+            var effective_con = 0.0;
+            if con_sum != 0.0 {
+                effective_con = 2.0 * cell_con * neighbor_con / con_sum;
+            }
+
+            let geometry = volume / (cell_size[axis] * cell_size[axis]);
+            delta = delta + (neighbor_temperature - temperature) * effective_con * params.deltatime * geometry;
+            face_flows[face] = delta;
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::world::{Float, SimState, SimWorld};
+
+use super::{
+    SimRunner,
+    cpu::{CELL_KERLEL, InterfaceAveraging, convective_conductance, neighbor_conductance},
+};
+
+/// Simulation Runner that uses implicit (backward Euler) integration, so it remains stable with
+/// timesteps far beyond [SimWorld::max_stable_timestep]
+///
+/// Each timestep solves for the temperatures at the end of the step with a preconditioned
+/// conjugate gradient iteration, using the conductivities and heat capacities at the start of
+/// the step. Heat flows between neighboring faces, as with the default stencil of
+/// [super::cpu::CPUSimRunner]. Energy is exchanged in equal and opposite flows between cells, so
+/// it is conserved even when the iteration stops before converging.
+///
+/// Large timesteps are stable but not accurate, so results only match the explicit runners once
+/// the world is near a steady state. Latent heat is not included in the heat capacities used by
+/// the solve, so steps across phase changes are less accurate.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material::{self, Axis}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner, implicit::ImplicitSimRunner}};
+/// # use futures::executor::block_on;
+/// let bar = AABBVolume::new(0.0, 0.0, 0.0, 0.1, 0.01, 0.01);
+/// let world = SimWorldBuilder::new(0.1, 0.01, 0.01).with_default_material(material::COPPER).build(0.01);
+/// let state = world.set_sim_state_temperature_gradient(world.get_blank_sim_state(), &bar, 300.0, 400.0, Axis::X).unwrap();
+///
+/// // A timestep 100 times the explicit limit remains stable, without losing energy
+/// let runner = ImplicitSimRunner::default();
+/// let timestep = world.max_stable_timestep() * 100.0;
+/// let result = block_on(runner.advance_simulation(&world, &state, timestep * 20.0, timestep)).unwrap();
+/// let (min, max) = world.temperature_extremes(&result).unwrap();
+/// assert!(min >= 299.99 && max <= 400.01);
+/// let (before, after) = (world.total_energy(&state), world.total_energy(&result));
+/// assert!(((after - before) / before).abs() < 1e-5);
+///
+/// // Both runners settle to the same steady state
+/// let explicit = CPUSimRunner::default();
+/// let (explicit_result, _) = block_on(explicit.advance_to_steady_state(&world, &state, world.max_stable_timestep(), 0.0001, 100_000)).unwrap();
+/// let (implicit_result, _) = block_on(runner.advance_to_steady_state(&world, &state, timestep, 0.001, 1_000)).unwrap();
+/// let explicit_mean = world.mean_temperature(&explicit_result).unwrap();
+/// let implicit_mean = world.mean_temperature(&implicit_result).unwrap();
+/// assert!((explicit_mean - implicit_mean).abs() < 0.01);
+/// assert!(world.temperature_extremes(&implicit_result).map(|(min, max)| max - min).unwrap() < 0.01);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ImplicitSimRunner {
+    tolerance: f32,
+    max_iterations: usize,
+}
+
+impl ImplicitSimRunner {
+    /// Create a new implicit runner
+    ///
+    /// The iteration solving each timestep stops once no cell's temperature is estimated to be
+    /// in error by more than `tolerance` kelvin, or after `max_iterations` iterations.
+    pub fn new(tolerance: f32, max_iterations: usize) -> ImplicitSimRunner {
+        ImplicitSimRunner {
+            tolerance,
+            max_iterations: max_iterations.max(1),
+        }
+    }
+
+    /// Get the tolerance in kelvin the iteration solving each timestep stops at
+    pub fn get_tolerance(&self) -> f32 {
+        self.tolerance
+    }
+
+    /// Get the maximum number of iterations used to solve each timestep
+    pub fn get_max_iterations(&self) -> usize {
+        self.max_iterations
+    }
+}
+
+impl Default for ImplicitSimRunner {
+    /// An implicit runner with a tolerance of 1e-5 kelvin and at most 1000 iterations per step
+    fn default() -> Self {
+        return ImplicitSimRunner::new(1e-5, 1000);
+    }
+}
+
+impl<E: Float> SimRunner<E> for ImplicitSimRunner {
+    /// Advance the given simulation state in the given world by a single timestep
+    fn advance_step(
+        &self,
+        world: &SimWorld,
+        mut current_state: SimState<E>,
+        deltatime: f32,
+    ) -> SimState<E> {
+        // The system is solved in double precision whatever the precision of the state
+        let temperatures: Vec<f64> = world
+            .precise_temperature_field(&current_state)
+            .expect("Runners must only be given valid states")
+            .into_iter()
+            .map(|t| t.to_f64())
+            .collect();
+        let system = LinearSystem::new(world, &temperatures, deltatime as f64);
+        let new_temperatures = system.solve(self.tolerance as f64, self.max_iterations);
+
+        // Exchange energy using the end of step temperatures, each flow is applied to both
+        // cells so that energy is conserved
+        let cell_volume = world.get_cell_volume();
+        let energy_deltas: Vec<E> = (0..temperatures.len())
+            .map(|i| {
+                let flow: f64 = system.neighbors[i]
+                    .iter()
+                    .map(|(j, conductance)| {
+                        conductance * (new_temperatures[*j] - new_temperatures[i])
+                    })
+                    .sum();
+                let convection = system.convection[i]
+                    * (world.get_ambient_temperature() as f64 - new_temperatures[i]);
+                let source = world.get_heat_source(i) as f64 * cell_volume;
+                E::from_f64((flow + convection + source) * deltatime as f64)
+            })
+            .collect();
+
+        current_state.apply_deltas(energy_deltas);
+        world.apply_fixed_temperatures(&mut current_state);
+        return current_state;
+    }
+
+    /// Implicit integration is stable at any timestep
+    fn max_stable_timestep(&self, _world: &SimWorld) -> f64 {
+        return f64::INFINITY;
+    }
+}
+
+/// The symmetric system of equations for the temperatures at the end of an implicit timestep
+///
+/// For each free cell `C (T' - T) = dt (Σ G (T'n - T') + S)`, where `C` is the cell's heat
+/// capacity, `G` the conductance to each neighbor, or to the surrounding fluid at the ambient
+/// temperature, and `S` the heat source. Cells held at a fixed
+/// temperature, or without any heat capacity, keep their temperature and are moved to the right
+/// hand side of their neighbors' equations.
+struct LinearSystem {
+    // Conductance in W / K to each neighbor of every cell
+    neighbors: Vec<Vec<(usize, f64)>>,
+    // Conductance in W / K to the surrounding fluid of every cell
+    convection: Vec<f64>,
+    // The fixed temperature of each cell which can not change during the step
+    pinned: Vec<Option<f64>>,
+    // Diagonal of the system matrix
+    diagonal: Vec<f64>,
+    // Right hand side of the system
+    rhs: Vec<f64>,
+    deltatime: f64,
+}
+
+impl LinearSystem {
+    fn new(world: &SimWorld, temperatures: &[f64], deltatime: f64) -> LinearSystem {
+        let cell_count = temperatures.len();
+        let materials = world.get_materials();
+        let mat_map = world.get_material_map();
+        let cell_volume = world.get_cell_volume();
+
+        let neighbors: Vec<Vec<(usize, f64)>> = (0..cell_count)
+            .map(|i| {
+                CELL_KERLEL
+                    .iter()
+                    .filter_map(|(dx, dy, dz, _)| {
+                        neighbor_conductance(
+                            world,
+                            temperatures,
+                            i,
+                            (*dx, *dy, *dz),
+                            InterfaceAveraging::Harmonic,
+                        )
+                    })
+                    .map(|(j, conductivity, face_area, distance)| {
+                        (j, conductivity as f64 * face_area / distance)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let convection: Vec<f64> = (0..cell_count)
+            .map(|i| convective_conductance(world, i) as f64)
+            .collect();
+
+        let capacities: Vec<f64> = (0..cell_count)
+            .map(|i| {
+                let material = mat_map[materials[i] as usize];
+                let temperature = temperatures[i] as f32;
+                material.get_density(temperature) as f64
+                    * material.get_specific_heat(temperature) as f64
+                    * cell_volume
+            })
+            .collect();
+
+        let pinned: Vec<Option<f64>> = (0..cell_count)
+            .map(|i| match world.get_fixed_temperature(i) {
+                Some(t) => Some(t as f64),
+                None if capacities[i] <= 0.0 => Some(temperatures[i]),
+                None => None,
+            })
+            .collect();
+
+        let mut diagonal = vec![1.0; cell_count];
+        let mut rhs = vec![0.0; cell_count];
+        for i in 0..cell_count {
+            if let Some(t) = pinned[i] {
+                rhs[i] = t;
+                continue;
+            }
+            diagonal[i] = capacities[i] + deltatime * convection[i];
+            rhs[i] = capacities[i] * temperatures[i]
+                + deltatime * convection[i] * world.get_ambient_temperature() as f64
+                + deltatime * world.get_heat_source(i) as f64 * cell_volume;
+            for (j, conductance) in neighbors[i].iter() {
+                diagonal[i] += deltatime * conductance;
+                if let Some(t) = pinned[*j] {
+                    rhs[i] += deltatime * conductance * t;
+                }
+            }
+        }
+
+        return LinearSystem {
+            neighbors,
+            convection,
+            pinned,
+            diagonal,
+            rhs,
+            deltatime,
+        };
+    }
+
+    /// Multiply a vector of temperatures by the system matrix
+    fn apply(&self, x: &[f64]) -> Vec<f64> {
+        return (0..x.len())
+            .map(|i| {
+                if self.pinned[i].is_some() {
+                    return x[i];
+                }
+                let coupling: f64 = self.neighbors[i]
+                    .iter()
+                    .filter(|(j, _)| self.pinned[*j].is_none())
+                    .map(|(j, conductance)| conductance * x[*j])
+                    .sum();
+                self.diagonal[i] * x[i] - self.deltatime * coupling
+            })
+            .collect();
+    }
+
+    /// Solve the system with a Jacobi preconditioned conjugate gradient iteration
+    fn solve(&self, tolerance: f64, max_iterations: usize) -> Vec<f64> {
+        let dot = |a: &[f64], b: &[f64]| -> f64 { a.iter().zip(b).map(|(a, b)| a * b).sum() };
+        let precondition =
+            |r: &[f64]| -> Vec<f64> { r.iter().zip(&self.diagonal).map(|(r, d)| r / d).collect() };
+
+        // Start from the solution with no heat flow between cells
+        let mut x: Vec<f64> = self
+            .rhs
+            .iter()
+            .zip(&self.diagonal)
+            .map(|(b, d)| b / d)
+            .collect();
+        let ax = self.apply(&x);
+        let mut r: Vec<f64> = self.rhs.iter().zip(ax).map(|(b, ax)| b - ax).collect();
+        let mut z = precondition(&r);
+        let mut p = z.clone();
+        let mut rz = dot(&r, &z);
+
+        for _ in 0..max_iterations {
+            // The preconditioned residual estimates the error in each temperature
+            if z.iter().all(|e| e.abs() <= tolerance) {
+                break;
+            }
+            let ap = self.apply(&p);
+            let pap = dot(&p, &ap);
+            if pap <= 0.0 {
+                break;
+            }
+            let alpha = rz / pap;
+            for i in 0..x.len() {
+                x[i] += alpha * p[i];
+                r[i] -= alpha * ap[i];
+            }
+            z = precondition(&r);
+            let new_rz = dot(&r, &z);
+            let beta = new_rz / rz;
+            rz = new_rz;
+            for i in 0..p.len() {
+                p[i] = z[i] + beta * p[i];
+            }
+        }
+        return x;
+    }
+}
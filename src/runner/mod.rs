@@ -1,27 +1,799 @@
-use std::{error::Error, future::Future};
+use std::{
+    error::Error,
+    future::Future,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
-use crate::world::{SimState, SimWorld};
+use crate::{
+    sensor::SensorArray,
+    world::{Checkpoint, Float, SimState, SimWorld},
+};
 
 /// Single Threaded CPU based simulator
 pub mod cpu;
+/// GPU based simulator using wgpu, requires the `wgpu` feature
+#[cfg(feature = "wgpu")]
+pub mod gpu;
+/// Implicit CPU based simulator, for timesteps beyond the explicit stability limit
+pub mod implicit;
+/// Multithreaded CPU based simulator using rayon, requires the `rayon` feature
+#[cfg(feature = "rayon")]
+pub mod rayon;
+/// Single Threaded CPU based simulator using SIMD vectors, requires the `simd` feature
+#[cfg(feature = "simd")]
+pub mod simd;
+/// Multithreaded CPU based simulator
+pub mod threaded;
 
 #[derive(Debug)]
 /// Simulation Runtime Error
-pub enum SimError {
+pub enum SimError<E: Float = f32> {
     /// The simulation state passed in is not a valid state of the simulatin
     SimStateInvalid,
+    /// The timestep passed in is larger than the runner's [SimRunner::max_stable_timestep]
+    TimestepUnstable,
+    /// The timestep passed in is not a positive time
+    InvalidTimestep,
+    /// The simulation was cancelled, holding the state reached before cancellation
+    Cancelled(SimState<E>),
+    /// The simulation did not reach a steady state within the allowed number of timesteps
+    DidNotConverge,
+    /// An energy became NaN or infinite, see [SimRunner::advance_simulation_checked]
+    NumericalInstability {
+        /// The number of the timestep which produced the value, counting from 1
+        step: usize,
+        /// The index of the first cell holding a non-finite energy
+        first_bad_cell: usize,
+    },
 }
 
 /// Trait for simulation runners
 ///
 /// Simulation runners are responsible for advacing the state of a thermal simulation in time.
-pub trait SimRunner {
+pub trait SimRunner<E: Float = f32>: Sync {
+    /// Advance a simulation state by a single timestep
+    ///
+    /// A single timestep has no simulated time, so scheduled heat sources are left to the
+    /// advance methods, see [SimWorld::apply_scheduled_heat_sources]. Panics if the state is not a
+    /// valid state of the world.
+    fn advance_step(
+        &self,
+        world: &SimWorld,
+        current_state: SimState<E>,
+        timestep: f32,
+    ) -> SimState<E>;
+
+    /// Get the largest timestep this runner can stably advance a world with
+    ///
+    /// Explicit runners are limited by [SimWorld::max_stable_timestep], which is the default.
+    fn max_stable_timestep(&self, world: &SimWorld) -> f64 {
+        return world.max_stable_timestep();
+    }
+
     /// Advance the simulation by a given ammount of time, with the given timestep
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, SimError, cpu::CPUSimRunner}};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// let state = world.get_blank_sim_state();
+    /// let timestep = world.max_stable_timestep() * 2.0;
+    /// let result = futures::executor::block_on(CPUSimRunner::default().advance_simulation(&world, &state, timestep, timestep));
+    /// assert!(matches!(result, Err(SimError::TimestepUnstable)));
+    ///
+    /// // Timesteps which would never finish the advance are rejected
+    /// for timestep in [0.0, -1.0, f64::NAN] {
+    ///     let result = futures::executor::block_on(CPUSimRunner::default().advance_simulation(&world, &state, 1.0, timestep));
+    ///     assert!(matches!(result, Err(SimError::InvalidTimestep)));
+    /// }
+    /// ```
     fn advance_simulation(
         &self,
         world: &SimWorld,
-        current_state: &SimState,
+        current_state: &SimState<E>,
+        advace_time: f64,
+        timestep: f64,
+    ) -> impl std::future::Future<Output = Result<SimState<E>, SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let (active_state, _) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                0.0,
+                advace_time,
+                timestep,
+                |_, _, _| Ok(()),
+                None,
+            )?;
+            return Ok(active_state);
+        }
+    }
+
+    /// Advance the simulation by a given ammount of time, with the given timestep, calling
+    /// `progress` with the fraction of the time advanced after every timestep
+    ///
+    /// The callback is only ever called from the task awaiting the advance, never from runner
+    /// worker threads.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, threaded::ThreadedSimRunner}};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// let state = world.get_blank_sim_state();
+    ///
+    /// let mut reports = Vec::new();
+    /// let runner = ThreadedSimRunner::new(4, 64);
+    /// let advance = runner.advance_simulation_with_progress(&world, &state, 10.0, 1.0, |fraction| reports.push(fraction));
+    /// futures::executor::block_on(advance).unwrap();
+    ///
+    /// assert_eq!(reports.len(), 10);
+    /// assert_eq!(reports.last(), Some(&1.0));
+    /// assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+    /// ```
+    fn advance_simulation_with_progress(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
+        advace_time: f64,
+        timestep: f64,
+        mut progress: impl FnMut(f64) + Send,
+    ) -> impl std::future::Future<Output = Result<SimState<E>, SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let (active_state, _) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                0.0,
+                advace_time,
+                timestep,
+                |_, _, elapsed| {
+                    progress(elapsed / advace_time);
+                    return Ok(());
+                },
+                None,
+            )?;
+            return Ok(active_state);
+        }
+    }
+
+    /// Advance the simulation by a given ammount of time, with the given timestep, stopping early
+    /// if `cancel` is set
+    ///
+    /// The flag is checked before every timestep, so a cancelled simulation stops after the
+    /// current step and returns [SimError::Cancelled] holding the partially advanced state.
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::sync::{Arc, atomic::{AtomicBool, AtomicUsize, Ordering}};
+    /// # use thermal_rs::{world::{SimState, SimWorld, SimWorldBuilder}, material, volume::AABBVolume, runner::{SimRunner, SimError, cpu::CPUSimRunner}};
+    /// // A runner which requests cancellation after its third step
+    /// struct CancellingRunner {
+    ///     steps: AtomicUsize,
+    ///     cancel: Arc<AtomicBool>,
+    /// }
+    ///
+    /// impl SimRunner for CancellingRunner {
+    ///     fn advance_step(&self, world: &SimWorld, state: SimState, timestep: f32) -> SimState {
+    ///         if self.steps.fetch_add(1, Ordering::Relaxed) + 1 == 3 {
+    ///             self.cancel.store(true, Ordering::Relaxed);
+    ///         }
+    ///         return CPUSimRunner::default().advance_step(world, state, timestep);
+    ///     }
+    /// }
+    ///
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// let state = world.get_blank_sim_state();
+    /// let cancel = Arc::new(AtomicBool::new(false));
+    /// let runner = CancellingRunner { steps: AtomicUsize::new(0), cancel: cancel.clone() };
+    ///
+    /// let advance = runner.advance_simulation_cancellable(&world, &state, 10.0, 1.0, cancel);
+    /// let result = futures::executor::block_on(advance);
+    /// assert!(matches!(result, Err(SimError::Cancelled(_))));
+    /// assert_eq!(runner.steps.load(Ordering::Relaxed), 3);
+    /// ```
+    fn advance_simulation_cancellable(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
+        advace_time: f64,
+        timestep: f64,
+        cancel: Arc<AtomicBool>,
+    ) -> impl std::future::Future<Output = Result<SimState<E>, SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let (active_state, _) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                0.0,
+                advace_time,
+                timestep,
+                |_, _, _| Ok(()),
+                Some(&cancel),
+            )?;
+            return Ok(active_state);
+        }
+    }
+
+    /// Advance the simulation by a given ammount of time, with the given timestep, as a stream
+    /// of snapshots of the state taken every `every` timesteps
+    ///
+    /// The final timestep is always followed by a snapshot, so the last snapshot is the state
+    /// [SimRunner::advance_simulation] would return. Each snapshot is calculated when the stream
+    /// is polled for it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::{block_on, block_on_stream};
+    /// let world = SimWorldBuilder::new(0.04, 0.01, 0.01).with_default_material(material::COPPER).build(0.01);
+    /// let state = world.get_ambient_sim_state();
+    ///
+    /// // Ten timesteps with a snapshot every third step
+    /// let runner = CPUSimRunner::default();
+    /// let timestep = world.max_stable_timestep();
+    /// let stream = runner.advance_simulation_stream(&world, &state, timestep * 10.0, timestep, 3).unwrap();
+    /// let frames: Vec<_> = block_on_stream(stream).collect();
+    /// assert_eq!(frames.len(), 4);
+    ///
+    /// let result = block_on(runner.advance_simulation(&world, &state, timestep * 10.0, timestep)).unwrap();
+    /// assert_eq!(frames.last().unwrap().get_energies(), result.get_energies());
+    /// ```
+    fn advance_simulation_stream<'a>(
+        &'a self,
+        world: &'a SimWorld,
+        current_state: &SimState<E>,
+        advace_time: f64,
+        timestep: f64,
+        every: usize,
+    ) -> Result<impl futures_core::Stream<Item = SimState<E>> + Send + 'a, SimError<E>> {
+        if !world.is_state_valid(current_state) {
+            return Err(SimError::SimStateInvalid);
+        }
+        check_timestep(self, world, timestep)?;
+        return Ok(SnapshotStream {
+            runner: self,
+            world,
+            state: Some(current_state.clone()),
+            sim_time: 0.0,
+            remaining_time: advace_time,
+            timestep,
+            every: every.max(1),
+        });
+    }
+
+    /// Advance the simulation by a given ammount of time, with the given timestep, checking the
+    /// state for NaN or infinite energies after every timestep
+    ///
+    /// Unlike [SimRunner::advance_simulation], the timestep is not checked against
+    /// [SimRunner::max_stable_timestep], so timesteps close to or beyond the limit can be tried.
+    /// If they prove unstable, this fails with [SimError::NumericalInstability] at the first
+    /// timestep to produce a non-finite energy, rather than returning a corrupted state. Timesteps
+    /// which are not positive are still rejected with [SimError::InvalidTimestep]. Checking every
+    /// cell after every timestep makes this slower than [SimRunner::advance_simulation].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Axis}, volume::AABBVolume, runner::{SimRunner, SimError, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// // Instability needs a temperature difference to grow from
+    /// let bar = AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.01, 0.01);
+    /// let world = SimWorldBuilder::new(0.05, 0.01, 0.01).with_default_material(material::COPPER).build(0.01);
+    /// let state = world.set_sim_state_temperature_gradient(world.get_blank_sim_state(), &bar, 300.0, 400.0, Axis::X).unwrap();
+    ///
+    /// // A stable timestep runs to completion
+    /// let runner = CPUSimRunner::default();
+    /// let timestep = world.max_stable_timestep();
+    /// assert!(block_on(runner.advance_simulation_checked(&world, &state, timestep * 100.0, timestep)).is_ok());
+    ///
+    /// // Ten times the stable timestep blows up within a few dozen steps
+    /// let result = block_on(runner.advance_simulation_checked(&world, &state, timestep * 1000.0, timestep * 10.0));
+    /// match result {
+    ///     Err(SimError::NumericalInstability { step, first_bad_cell }) => {
+    ///         assert!(step > 1 && step < 100);
+    ///         assert!(first_bad_cell < 5);
+    ///     }
+    ///     _ => panic!("The simulation should have gone unstable"),
+    /// }
+    ///
+    /// let zero = block_on(runner.advance_simulation_checked(&world, &state, 1.0, 0.0));
+    /// assert!(matches!(zero, Err(SimError::InvalidTimestep)));
+    /// ```
+    fn advance_simulation_checked(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
+        advace_time: f64,
+        timestep: f64,
+    ) -> impl std::future::Future<Output = Result<SimState<E>, SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_positive_timestep(timestep)?;
+            let (active_state, _) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                0.0,
+                advace_time,
+                timestep,
+                |state, step, _| match state.first_non_finite() {
+                    Some(first_bad_cell) => Err(SimError::NumericalInstability {
+                        step,
+                        first_bad_cell,
+                    }),
+                    None => Ok(()),
+                },
+                None,
+            )?;
+            return Ok(active_state);
+        }
+    }
+
+    /// Advance the simulation by a given ammount of time, with the given timestep, recording
+    /// every sensor in the array after every timestep
+    ///
+    /// Readings are timed from the array's latest readings, so advancing again with the same
+    /// array continues its traces. See [SensorArray] for an example.
+    fn advance_simulation_with_sensors(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
+        advace_time: f64,
+        timestep: f64,
+        sensors: &mut SensorArray,
+    ) -> impl std::future::Future<Output = Result<SimState<E>, SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let start_time = sensors.get_sim_time();
+            let (active_state, _) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                start_time,
+                advace_time,
+                timestep,
+                |state, _, elapsed| {
+                    sensors.record(world, state, start_time + elapsed);
+                    return Ok(());
+                },
+                None,
+            )?;
+            return Ok(active_state);
+        }
+    }
+
+    /// Continue a simulation from a checkpoint by a given ammount of time, with the given
+    /// timestep, returning a checkpoint of the state reached
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{Checkpoint, SimWorldBuilder}, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0);
+    /// let world = SimWorldBuilder::new(4.0, 1.0, 1.0)
+    ///     .with_material(material::COPPER, Box::new(everything.clone()))
+    ///     .build(1.0);
+    /// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    ///
+    /// let runner = CPUSimRunner::default();
+    /// let mut checkpoint = Checkpoint::new(world, state, 0.0, 1.0);
+    /// for _ in 0..3 {
+    ///     checkpoint = block_on(runner.resume(&checkpoint, 5.0, 0.5)).unwrap();
+    /// }
+    /// assert_eq!(checkpoint.get_sim_time(), 15.0);
+    /// assert_eq!(checkpoint.get_timestep(), 0.5);
+    /// ```
+    fn resume(
+        &self,
+        checkpoint: &Checkpoint<E>,
+        additional_time: f64,
+        timestep: f64,
+    ) -> impl std::future::Future<Output = Result<Checkpoint<E>, SimError<E>>> + Send {
+        async move {
+            let world = checkpoint.get_world();
+            if !world.is_state_valid(checkpoint.get_state()) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let (state, _) = step_through(
+                self,
+                world,
+                checkpoint.get_state().clone(),
+                checkpoint.get_sim_time(),
+                additional_time,
+                timestep,
+                |_, _, _| Ok(()),
+                None,
+            )?;
+            return Ok(Checkpoint::new(
+                world.clone(),
+                state,
+                checkpoint.get_sim_time() + additional_time,
+                timestep,
+            ));
+        }
+    }
+
+    /// Advance the simulation by a given ammount of time, with the given timestep, returning
+    /// statistics about the advance along with the final state
+    ///
+    /// Finding the largest change in temperature requires the temperature of every cell after
+    /// every timestep, so this is slower than [SimRunner::advance_simulation].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// // A heated block of copper
+    /// let mut world = SimWorldBuilder::new(1.0, 1.0, 1.0).with_default_material(material::COPPER).build(1.0);
+    /// world.add_heat_source(&AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0), 1.0e5);
+    /// let state = world.get_ambient_sim_state();
+    ///
+    /// let runner = CPUSimRunner::default();
+    /// let advance = runner.advance_simulation_stats(&world, &state, 10.5, 1.0);
+    /// let (result, stats) = futures::executor::block_on(advance).unwrap();
+    /// assert_eq!(stats.get_steps(), 11);
+    /// assert!(stats.get_max_temp_change() > 0.0 && stats.get_max_temp_change() < 100.0);
+    /// ```
+    fn advance_simulation_stats(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
         advace_time: f64,
         timestep: f64,
-    ) -> impl std::future::Future<Output = Result<SimState, SimError>> + Send;
+    ) -> impl std::future::Future<Output = Result<(SimState<E>, SimStats), SimError<E>>> + Send
+    {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let start = std::time::Instant::now();
+            let mut temperatures = world
+                .temperature_field(current_state)
+                .map_err(|_| SimError::SimStateInvalid)?;
+            let mut stats = SimStats::default();
+            let (active_state, steps) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                0.0,
+                advace_time,
+                timestep,
+                |state, _, _| {
+                    let new_temperatures = world
+                        .temperature_field(state)
+                        .map_err(|_| SimError::SimStateInvalid)?;
+                    stats.max_temp_change = temperatures
+                        .iter()
+                        .zip(new_temperatures.iter())
+                        .map(|(old, new)| (new - old).abs())
+                        .fold(stats.max_temp_change, f32::max);
+                    temperatures = new_temperatures;
+                    return Ok(());
+                },
+                None,
+            )?;
+            stats.steps = steps;
+            stats.elapsed = start.elapsed();
+            return Ok((active_state, stats));
+        }
+    }
+
+    /// Advance the simulation by a given ammount of time, using the largest stable timestep
+    ///
+    /// The result is identical to calling [SimRunner::advance_simulation] with a timestep of
+    /// [SimRunner::max_stable_timestep].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let world = SimWorldBuilder::new(0.04, 0.01, 0.01).with_default_material(material::COPPER).build(0.01);
+    /// let state = world.get_ambient_sim_state();
+    ///
+    /// // A minute is thousands of stable steps at this resolution
+    /// let runner = CPUSimRunner::default();
+    /// let advance = block_on(runner.advance_simulation_adaptive(&world, &state, 60.0)).unwrap();
+    /// assert!(advance.get_step_count() > 100);
+    ///
+    /// let manual = block_on(runner.advance_simulation(&world, &state, 60.0, world.max_stable_timestep())).unwrap();
+    /// assert_eq!(manual.get_energies(), advance.get_state().get_energies());
+    /// ```
+    fn advance_simulation_adaptive(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
+        advace_time: f64,
+    ) -> impl std::future::Future<Output = Result<AdaptiveAdvance<E>, SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            let timestep = self.max_stable_timestep(world).min(advace_time);
+            let (state, step_count) = step_through(
+                self,
+                world,
+                current_state.clone(),
+                0.0,
+                advace_time,
+                timestep,
+                |_, _, _| Ok(()),
+                None,
+            )?;
+            return Ok(AdaptiveAdvance { state, step_count });
+        }
+    }
+
+    /// Advance the simulation with the given timestep until it reaches a steady state, returning
+    /// the final state and the number of timesteps taken
+    ///
+    /// A steady state is reached when no cell's temperature changes by `tolerance` kelvin or
+    /// more in a single timestep. Returns [SimError::DidNotConverge] if that takes more than
+    /// `max_steps` timesteps.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Axis}, volume::AABBVolume, runner::{SimRunner, SimError, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let bar = AABBVolume::new(0.0, 0.0, 0.0, 0.04, 0.01, 0.01);
+    /// let world = SimWorldBuilder::new(0.04, 0.01, 0.01).with_default_material(material::COPPER).build(0.01);
+    /// let state = world.set_sim_state_temperature_gradient(world.get_blank_sim_state(), &bar, 300.0, 400.0, Axis::X).unwrap();
+    ///
+    /// let runner = CPUSimRunner::default();
+    /// let timestep = world.max_stable_timestep();
+    /// let (result, steps) = block_on(runner.advance_to_steady_state(&world, &state, timestep, 0.001, 100_000)).unwrap();
+    /// assert!(steps > 1);
+    ///
+    /// // An insulated bar settles to a uniform temperature
+    /// let (min, max) = world.temperature_extremes(&result).unwrap();
+    /// assert!(max - min < 0.1);
+    ///
+    /// let too_few = block_on(runner.advance_to_steady_state(&world, &state, timestep, 0.001, 2));
+    /// assert!(matches!(too_few, Err(SimError::DidNotConverge)));
+    /// ```
+    fn advance_to_steady_state(
+        &self,
+        world: &SimWorld,
+        current_state: &SimState<E>,
+        timestep: f64,
+        tolerance: f32,
+        max_steps: usize,
+    ) -> impl std::future::Future<Output = Result<(SimState<E>, usize), SimError<E>>> + Send {
+        async move {
+            if !world.is_state_valid(current_state) {
+                return Err(SimError::SimStateInvalid);
+            }
+            check_timestep(self, world, timestep)?;
+            let mut active_state = current_state.clone();
+            let mut temperatures = world
+                .temperature_field(&active_state)
+                .map_err(|_| SimError::SimStateInvalid)?;
+            for step in 1..=max_steps {
+                let sim_time = (step - 1) as f64 * timestep;
+                active_state = scheduled_step(self, world, active_state, sim_time, timestep);
+                let new_temperatures = world
+                    .temperature_field(&active_state)
+                    .map_err(|_| SimError::SimStateInvalid)?;
+                let max_change = temperatures
+                    .iter()
+                    .zip(new_temperatures.iter())
+                    .map(|(old, new)| (new - old).abs())
+                    .fold(0.0, f32::max);
+                if max_change < tolerance {
+                    return Ok((active_state, step));
+                }
+                temperatures = new_temperatures;
+            }
+            return Err(SimError::DidNotConverge);
+        }
+    }
+}
+
+/// The result of an adaptive advance of a simulation
+#[derive(Debug, Clone)]
+pub struct AdaptiveAdvance<E: Float = f32> {
+    // The state of the simulation after the advance
+    state: SimState<E>,
+    // The number of timesteps taken to advance the simulation
+    step_count: usize,
+}
+
+impl<E: Float> AdaptiveAdvance<E> {
+    /// Get the state of the simulation after the advance
+    pub fn get_state(&self) -> &SimState<E> {
+        &self.state
+    }
+
+    /// Get the number of timesteps taken to advance the simulation
+    pub fn get_step_count(&self) -> usize {
+        self.step_count
+    }
+
+    /// Take the state of the simulation after the advance
+    pub fn into_state(self) -> SimState<E> {
+        self.state
+    }
+}
+
+/// Statistics about an advance of a simulation
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimStats {
+    // The number of timesteps taken
+    steps: usize,
+    // The wall clock time taken to advance the simulation
+    elapsed: std::time::Duration,
+    // The largest change in temperature of any cell in a single timestep
+    max_temp_change: f32,
+}
+
+impl SimStats {
+    /// Get the number of timesteps taken
+    pub fn get_steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Get the wall clock time taken to advance the simulation
+    pub fn get_elapsed(&self) -> std::time::Duration {
+        self.elapsed
+    }
+
+    /// Get the largest change in temperature in kelvin of any cell in a single timestep. Large
+    /// changes suggest the timestep is too large to be accurate
+    pub fn get_max_temp_change(&self) -> f32 {
+        self.max_temp_change
+    }
+}
+
+/// A stream of snapshots of an advancing simulation, see [SimRunner::advance_simulation_stream]
+struct SnapshotStream<'a, E: Float, R: SimRunner<E> + ?Sized> {
+    runner: &'a R,
+    world: &'a SimWorld,
+    // Only taken while a timestep is being advanced
+    state: Option<SimState<E>>,
+    // The simulated time in seconds since the start of the advance
+    sim_time: f64,
+    remaining_time: f64,
+    timestep: f64,
+    every: usize,
+}
+
+// The stream holds no self references, so it can be moved freely
+impl<E: Float, R: SimRunner<E> + ?Sized> Unpin for SnapshotStream<'_, E, R> {}
+
+impl<E: Float, R: SimRunner<E> + ?Sized> futures_core::Stream for SnapshotStream<'_, E, R> {
+    type Item = SimState<E>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        if self.remaining_time <= 0.0 {
+            return std::task::Poll::Ready(None);
+        }
+        for _ in 0..self.every {
+            if self.remaining_time <= 0.0 {
+                break;
+            }
+            let state = self
+                .state
+                .take()
+                .expect("The state is only taken during a timestep");
+            let step = self.timestep.min(self.remaining_time);
+            let sim_time = self.sim_time;
+            self.state = Some(scheduled_step(
+                self.runner,
+                self.world,
+                state,
+                sim_time,
+                step,
+            ));
+            self.sim_time += step;
+            self.remaining_time -= self.timestep;
+        }
+        return std::task::Poll::Ready(self.state.clone());
+    }
+}
+
+/// Check a timestep is positive, so an advance with it finishes
+fn check_positive_timestep<E: Float>(timestep: f64) -> Result<(), SimError<E>> {
+    if timestep.is_nan() || timestep <= 0.0 {
+        return Err(SimError::InvalidTimestep);
+    }
+    return Ok(());
+}
+
+/// Check a timestep is positive, and no larger than the runner's
+/// [SimRunner::max_stable_timestep] for the world
+fn check_timestep<E: Float, R: SimRunner<E> + ?Sized>(
+    runner: &R,
+    world: &SimWorld,
+    timestep: f64,
+) -> Result<(), SimError<E>> {
+    check_positive_timestep(timestep)?;
+    if timestep > runner.max_stable_timestep(world) {
+        return Err(SimError::TimestepUnstable);
+    }
+    return Ok(());
+}
+
+/// Advance a state by a single timestep starting at a simulated time in seconds, adding the
+/// energy of the world's scheduled heat sources over the timestep
+fn scheduled_step<E: Float, R: SimRunner<E> + ?Sized>(
+    runner: &R,
+    world: &SimWorld,
+    current_state: SimState<E>,
+    sim_time: f64,
+    timestep: f64,
+) -> SimState<E> {
+    let mut active_state = runner.advance_step(world, current_state, timestep as f32);
+    world.apply_scheduled_heat_sources(&mut active_state, sim_time, sim_time + timestep);
+    return active_state;
+}
+
+/// Advance a state by the given ammount of time in steps no larger than the given timestep,
+/// starting at a simulated time in seconds, returning the new state and the number of steps
+/// taken
+///
+/// `after_step` is called after every step with the new state, the number of steps taken and
+/// the time advanced so far, and any error it returns ends the advance. If `cancel` is
+/// set before a step begins, [SimError::Cancelled] is returned with the state reached so far.
+#[allow(clippy::too_many_arguments)]
+fn step_through<E: Float, R: SimRunner<E> + ?Sized>(
+    runner: &R,
+    world: &SimWorld,
+    mut active_state: SimState<E>,
+    start_time: f64,
+    advace_time: f64,
+    timestep: f64,
+    mut after_step: impl FnMut(&SimState<E>, usize, f64) -> Result<(), SimError<E>>,
+    cancel: Option<&AtomicBool>,
+) -> Result<(SimState<E>, usize), SimError<E>> {
+    let mut remaining_time = advace_time;
+    let mut step_count = 0;
+    while remaining_time > 0.0 {
+        if cancel.is_some_and(|flag| flag.load(Ordering::Relaxed)) {
+            return Err(SimError::Cancelled(active_state));
+        }
+        let sim_time = start_time + advace_time - remaining_time;
+        active_state = scheduled_step(
+            runner,
+            world,
+            active_state,
+            sim_time,
+            timestep.min(remaining_time),
+        );
+        remaining_time -= timestep;
+        step_count += 1;
+        after_step(
+            &active_state,
+            step_count,
+            advace_time - remaining_time.max(0.0),
+        )?;
+    }
+    return Ok((active_state, step_count));
 }
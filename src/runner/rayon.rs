@@ -0,0 +1,76 @@
+use ::rayon::prelude::*;
+
+use crate::world::{Float, SimState, SimWorld};
+
+use super::{
+    SimRunner,
+    cpu::{InterfaceAveraging, Stencil, cell_energy_delta},
+};
+
+/// Simulation Runner that uses the rayon thread pool to execute
+///
+/// Every cell's change in energy is calculated independently from the same starting
+/// temperatures, so results are identical to a [super::cpu::CPUSimRunner] with the same stencil
+/// and interface averaging.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::{CPUSimRunner, InterfaceAveraging, Stencil}, rayon::RayonSimRunner}};
+/// # use futures::executor::block_on;
+/// let world = SimWorldBuilder::new(4.0, 3.0, 2.0)
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 4.0, 3.0, 2.0)))
+///     .build(1.0);
+/// let mut state = world.get_blank_sim_state();
+/// state = world.set_sim_state_temperature(state, 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 4.0, 3.0, 2.0)).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)).unwrap();
+///
+/// let single = block_on(CPUSimRunner::default().advance_simulation(&world, &state, 10.0, 1.0)).unwrap();
+/// let parallel = block_on(RayonSimRunner::default().advance_simulation(&world, &state, 10.0, 1.0)).unwrap();
+/// assert_eq!(single.get_energies(), parallel.get_energies());
+///
+/// // Other stencils and averaging match the single threaded runner too
+/// let cpu = CPUSimRunner { stencil: Stencil::moore_26(), averaging: InterfaceAveraging::Arithmetic };
+/// let rayon = RayonSimRunner { stencil: Stencil::moore_26(), averaging: InterfaceAveraging::Arithmetic };
+/// let single = block_on(cpu.advance_simulation(&world, &state, 10.0, 0.5)).unwrap();
+/// let parallel = block_on(rayon.advance_simulation(&world, &state, 10.0, 0.5)).unwrap();
+/// assert_eq!(single.get_energies(), parallel.get_energies());
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RayonSimRunner {
+    /// The set of neighbors each cell exchanges heat with
+    pub stencil: Stencil,
+    /// How the conductivities of neighboring cells are combined
+    pub averaging: InterfaceAveraging,
+}
+
+impl<E: Float> SimRunner<E> for RayonSimRunner {
+    /// Advance the given simulation state in the given world by a single timestep
+    fn advance_step(
+        &self,
+        world: &SimWorld,
+        mut current_state: SimState<E>,
+        deltatime: f32,
+    ) -> SimState<E> {
+        let temperatures = world
+            .precise_temperature_field(&current_state)
+            .expect("Runners must only be given valid states");
+        let mut energy_deltas: Vec<E> = vec![E::default(); temperatures.len()];
+        energy_deltas
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(cell_index, delta)| {
+                *delta = cell_energy_delta(
+                    world,
+                    &temperatures,
+                    cell_index,
+                    deltatime,
+                    &self.stencil,
+                    self.averaging,
+                );
+            });
+
+        current_state.apply_deltas(energy_deltas);
+        world.apply_fixed_temperatures(&mut current_state);
+        return current_state;
+    }
+}
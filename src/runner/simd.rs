@@ -0,0 +1,192 @@
+use wide::f32x8;
+
+use crate::{
+    material::Axis,
+    world::{Float, SimState, SimWorld},
+};
+
+use super::{
+    SimRunner,
+    cpu::{CELL_KERLEL, convective_conductance},
+};
+
+/// Number of cells processed together in each vector
+const LANES: usize = 8;
+
+/// Simulation Runner that uses a single CPU thread, processing runs of cells along the x axis in
+/// SIMD vector lanes, requires the `simd` feature
+///
+/// Heat flows between the faces of neighboring cells, as with the default stencil of
+/// [super::cpu::CPUSimRunner]. Temperatures and flows are calculated in single precision vectors,
+/// and each cell's flows are summed at the precision of the state, so results agree with the
+/// scalar runner to within single precision error.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::{SimWorldBuilder, WorldBoundary}, material::{self, Axis}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner, simd::SimdSimRunner}};
+/// // A row length which is not a multiple of the vector width, with a periodic x axis
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.11, 0.03, 0.02);
+/// let mut world = SimWorldBuilder::new(0.11, 0.03, 0.02)
+///     .with_material(material::WATER, Box::new(everything.clone()))
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.02, 0.02)))
+///     .with_boundary(Axis::X, WorldBoundary::Periodic)
+///     .build(0.01);
+/// world.add_heat_source(&AABBVolume::new(0.1, 0.0, 0.0, 0.11, 0.01, 0.01), 1.0e6);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.01, 0.01)).unwrap();
+///
+/// let timestep = world.max_stable_timestep() as f32;
+/// let scalar = CPUSimRunner::default().advance_step(&world, state.clone(), timestep);
+/// let simd = SimdSimRunner::default().advance_step(&world, state, timestep);
+/// for (scalar, simd) in scalar.get_energies().iter().zip(simd.get_energies()) {
+///     assert!(((scalar - simd) / scalar).abs() <= f32::EPSILON);
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SimdSimRunner {}
+
+impl<E: Float> SimRunner<E> for SimdSimRunner {
+    /// Advance the given simulation state in the given world by a single timestep
+    fn advance_step(
+        &self,
+        world: &SimWorld,
+        mut current_state: SimState<E>,
+        deltatime: f32,
+    ) -> SimState<E> {
+        let temperatures = world
+            .temperature_field(&current_state)
+            .expect("Runners must only be given valid states");
+        let energy_deltas: Vec<E> = energy_deltas(world, &temperatures, deltatime);
+
+        current_state.apply_deltas(energy_deltas);
+        world.apply_fixed_temperatures(&mut current_state);
+        return current_state;
+    }
+}
+
+/// Load up to a vector of values from a slice, starting at an index. Lanes past the end of the
+/// slice are zero
+fn load(values: &[f32], start: usize) -> f32x8 {
+    let mut lanes = [0.0; LANES];
+    let end = (start + LANES).min(values.len());
+    lanes[..end - start].copy_from_slice(&values[start..end]);
+    return f32x8::new(lanes);
+}
+
+/// Calculate the change in energy of every cell over a timestep, one row along the x axis at a
+/// time
+///
+/// Flows are calculated in single precision vectors, and summed at the precision of the state.
+fn energy_deltas<E: Float>(world: &SimWorld, temperatures: &[f32], deltatime: f32) -> Vec<E> {
+    let (x_size, y_size, z_size) = (world.get_x_size(), world.get_y_size(), world.get_z_size());
+    let materials = world.get_materials();
+    let mat_map = world.get_material_map();
+    let cell_volume = world.get_cell_volume();
+
+    // Conductivity of every cell along each axis, at its current temperature. Materials without
+    // heat capacity do not conduct
+    let conductivities = [Axis::X, Axis::Y, Axis::Z].map(|axis| {
+        temperatures
+            .iter()
+            .zip(materials)
+            .map(|(t, m)| {
+                let material = &mat_map[*m as usize];
+                if !material.has_heat_capacity() {
+                    return 0.0;
+                }
+                material.get_thermal_conductivity_axis(*t, axis)
+            })
+            .collect::<Vec<f32>>()
+    });
+    // Geometry factor of the gap between neighbors along each axis, the cell volume over the
+    // squared distance between them
+    let geometry = [Axis::X, Axis::Y, Axis::Z]
+        .map(|axis| (cell_volume / world.get_cell_length(axis).powi(2)) as f32);
+
+    let mut energy_deltas = vec![E::default(); temperatures.len()];
+    let mut neighbor_temperatures = vec![0.0; x_size];
+    let mut neighbor_conductivities = vec![0.0; x_size];
+
+    for z in 0..z_size {
+        for y in 0..y_size {
+            let row = y * x_size + z * x_size * y_size;
+            let row_temperatures = &temperatures[row..row + x_size];
+            let row_deltas = &mut energy_deltas[row..row + x_size];
+
+            for (dx, dy, dz, axis) in CELL_KERLEL.iter() {
+                let axis_conductivities = &conductivities[*axis as usize];
+
+                // Gather the neighbors of the row, missing neighbors have no conductivity
+                if *dx == 0 {
+                    let neighbor_row = match world.get_ipos_index(
+                        0,
+                        y as i128 + *dy as i128,
+                        z as i128 + *dz as i128,
+                    ) {
+                        Some(i) => i,
+                        None => continue,
+                    };
+                    neighbor_temperatures
+                        .copy_from_slice(&temperatures[neighbor_row..neighbor_row + x_size]);
+                    neighbor_conductivities
+                        .copy_from_slice(&axis_conductivities[neighbor_row..neighbor_row + x_size]);
+                } else {
+                    // Within the row the neighbors are the row shifted by one cell, only the
+                    // cell at the end of the row needs the world's boundary condition
+                    let (shifted, edge) = if *dx > 0 {
+                        (0..x_size - 1, x_size - 1)
+                    } else {
+                        (1..x_size, 0)
+                    };
+                    let source = ((row + shifted.start) as isize + *dx as isize) as usize;
+                    let source = source..source + shifted.len();
+                    neighbor_temperatures[shifted.clone()]
+                        .copy_from_slice(&temperatures[source.clone()]);
+                    neighbor_conductivities[shifted].copy_from_slice(&axis_conductivities[source]);
+                    let neighbor =
+                        world.get_ipos_index(edge as i128 + *dx as i128, y as i128, z as i128);
+                    (neighbor_temperatures[edge], neighbor_conductivities[edge]) = match neighbor {
+                        Some(i) => (temperatures[i], axis_conductivities[i]),
+                        None => (0.0, 0.0),
+                    };
+                }
+
+                let row_conductivities = &axis_conductivities[row..row + x_size];
+                for start in (0..x_size).step_by(LANES) {
+                    let temperature = load(row_temperatures, start);
+                    let conductivity = load(row_conductivities, start);
+                    let neighbor_temperature = load(&neighbor_temperatures, start);
+                    let neighbor_conductivity = load(&neighbor_conductivities, start);
+
+                    // Harmonic mean of the two conductivities, as the halves of the gap are in
+                    // series
+                    let sum = conductivity + neighbor_conductivity;
+                    let effective = sum.simd_eq(f32x8::ZERO).select(
+                        f32x8::ZERO,
+                        f32x8::splat(2.0) * conductivity * neighbor_conductivity / sum,
+                    );
+                    let flow = (neighbor_temperature - temperature)
+                        * effective
+                        * f32x8::splat(deltatime)
+                        * f32x8::splat(geometry[*axis as usize]);
+
+                    let lanes = flow.to_array();
+                    let end = (start + LANES).min(x_size);
+                    for (delta, flow) in row_deltas[start..end].iter_mut().zip(lanes) {
+                        *delta += E::from_f32(flow);
+                    }
+                }
+            }
+
+            // Add the energy lost by convection and from any heat sources in the row
+            for (x, delta) in row_deltas.iter_mut().enumerate() {
+                let ambient_flow = convective_conductance(world, row + x)
+                    * (world.get_ambient_temperature() - row_temperatures[x]);
+                *delta += E::from_f32(ambient_flow * deltatime);
+                *delta +=
+                    E::from_f32(world.get_heat_source(row + x) * cell_volume as f32 * deltatime);
+            }
+        }
+    }
+    return energy_deltas;
+}
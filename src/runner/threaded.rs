@@ -0,0 +1,132 @@
+use crate::world::{Float, SimState, SimWorld};
+
+use super::{
+    SimRunner,
+    cpu::{InterfaceAveraging, Stencil, cell_energy_delta},
+};
+
+/// Simulation Runner that splits each timestep across multiple CPU threads
+///
+/// Cells are split into chunks which are handed out to the worker threads in turn. Every cell's
+/// change in energy is calculated independently from the same starting temperatures, so results
+/// are identical to a [super::cpu::CPUSimRunner] with the same stencil and interface averaging,
+/// regardless of the number of workers or the chunk size.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::{CPUSimRunner, InterfaceAveraging, Stencil}, threaded::ThreadedSimRunner}};
+/// # use futures::executor::block_on;
+/// let world = SimWorldBuilder::new(4.0, 3.0, 2.0)
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 4.0, 3.0, 2.0)))
+///     .build(1.0);
+/// let mut state = world.get_blank_sim_state();
+/// state = world.set_sim_state_temperature(state, 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 4.0, 3.0, 2.0)).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)).unwrap();
+///
+/// let single = block_on(CPUSimRunner::default().advance_simulation(&world, &state, 10.0, 1.0)).unwrap();
+/// let threaded = block_on(ThreadedSimRunner::new(4, 5).advance_simulation(&world, &state, 10.0, 1.0)).unwrap();
+/// assert_eq!(single.get_energies(), threaded.get_energies());
+///
+/// // Other stencils and averaging match the single threaded runner too
+/// let cpu = CPUSimRunner { stencil: Stencil::moore_26(), averaging: InterfaceAveraging::Arithmetic };
+/// let mut runner = ThreadedSimRunner::new(4, 5);
+/// runner.stencil = Stencil::moore_26();
+/// runner.averaging = InterfaceAveraging::Arithmetic;
+/// let single = block_on(cpu.advance_simulation(&world, &state, 10.0, 0.5)).unwrap();
+/// let threaded = block_on(runner.advance_simulation(&world, &state, 10.0, 0.5)).unwrap();
+/// assert_eq!(single.get_energies(), threaded.get_energies());
+/// ```
+pub struct ThreadedSimRunner {
+    workers: usize,
+    chunk_size: usize,
+    /// The set of neighbors each cell exchanges heat with, [Stencil::faces_6] by default
+    pub stencil: Stencil,
+    /// How the conductivities of neighboring cells are combined, [InterfaceAveraging::Harmonic]
+    /// by default
+    pub averaging: InterfaceAveraging,
+}
+
+impl ThreadedSimRunner {
+    /// Create a new threaded runner, with the number of worker threads to use and the number of
+    /// cells handed to a worker at a time, using the default stencil and interface averaging
+    ///
+    /// Neither the number of workers nor the chunk size changes the results, so they can be
+    /// tuned freely without breaking reproducibility.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner, threaded::ThreadedSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.05, 0.05);
+    /// let world = SimWorldBuilder::new(0.05, 0.05, 0.05)
+    ///     .with_material(material::WATER, Box::new(everything.clone()))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.03, 0.02, 0.05)))
+    ///     .build(0.01);
+    /// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.02, 0.02)).unwrap();
+    ///
+    /// let timestep = world.max_stable_timestep();
+    /// let expected = block_on(CPUSimRunner::default().advance_simulation(&world, &state, timestep * 50.0, timestep)).unwrap();
+    /// for (workers, chunk_size) in [(1, 1), (3, 4), (8, 16)] {
+    ///     let runner = ThreadedSimRunner::new(workers, chunk_size);
+    ///     let result = block_on(runner.advance_simulation(&world, &state, timestep * 50.0, timestep)).unwrap();
+    ///     assert_eq!(result.get_energies(), expected.get_energies());
+    /// }
+    /// ```
+    pub fn new(workers: usize, chunk_size: usize) -> ThreadedSimRunner {
+        ThreadedSimRunner {
+            workers: workers.max(1),
+            chunk_size: chunk_size.max(1),
+            stencil: Stencil::default(),
+            averaging: InterfaceAveraging::default(),
+        }
+    }
+}
+
+impl<E: Float> SimRunner<E> for ThreadedSimRunner {
+    /// Advance the given simulation state in the given world by a single timestep
+    fn advance_step(
+        &self,
+        world: &SimWorld,
+        mut current_state: SimState<E>,
+        deltatime: f32,
+    ) -> SimState<E> {
+        let temperatures = world
+            .precise_temperature_field(&current_state)
+            .expect("Runners must only be given valid states");
+        let mut energy_deltas: Vec<E> = vec![E::default(); temperatures.len()];
+
+        // Deal chunks of the delta buffer out to workers, each worker only writes to its own chunks
+        let mut worker_chunks: Vec<Vec<(usize, &mut [E])>> =
+            (0..self.workers).map(|_| Vec::new()).collect();
+        for (i, chunk) in energy_deltas.chunks_mut(self.chunk_size).enumerate() {
+            worker_chunks[i % self.workers].push((i * self.chunk_size, chunk));
+        }
+
+        let temperatures = &temperatures;
+        let stencil = &self.stencil;
+        let averaging = self.averaging;
+        std::thread::scope(|scope| {
+            for chunks in worker_chunks {
+                scope.spawn(move || {
+                    for (start, chunk) in chunks {
+                        for (offset, delta) in chunk.iter_mut().enumerate() {
+                            *delta = cell_energy_delta(
+                                world,
+                                temperatures,
+                                start + offset,
+                                deltatime,
+                                stencil,
+                                averaging,
+                            );
+                        }
+                    }
+                });
+            }
+        });
+
+        current_state.apply_deltas(energy_deltas);
+        world.apply_fixed_temperatures(&mut current_state);
+        return current_state;
+    }
+}
@@ -0,0 +1,110 @@
+use crate::world::{Float, SimState, SimWorld};
+
+/// A virtual thermocouple, recording the temperature of the cell containing a point over time
+#[derive(Debug, Clone)]
+pub struct Sensor {
+    position: (f64, f64, f64),
+    // (simulated time in seconds, temperature in kelvin) of every reading
+    history: Vec<(f64, f32)>,
+}
+
+impl Sensor {
+    /// Create a new Sensor at a world space point, with no readings
+    pub fn new(x: f64, y: f64, z: f64) -> Sensor {
+        Sensor {
+            position: (x, y, z),
+            history: Vec::new(),
+        }
+    }
+
+    /// Get the (x, y, z) world space position of the sensor in m
+    pub fn get_position(&self) -> (f64, f64, f64) {
+        self.position
+    }
+
+    /// Get every reading of the sensor in the order they were taken, as pairs of the simulated
+    /// time in seconds and the temperature in kelvin. Readings taken outside the world are NaN
+    pub fn get_history(&self) -> &[(f64, f32)] {
+        &self.history
+    }
+
+    /// Record the temperature of the sensor's cell in a simulation state, at a simulated time
+    pub fn record<E: Float>(&mut self, world: &SimWorld, sim_state: &SimState<E>, sim_time: f64) {
+        let (x, y, z) = self.position;
+        let temperature = world
+            .sample_temperature(sim_state, x, y, z)
+            .unwrap_or(f32::NAN);
+        self.history.push((sim_time, temperature));
+    }
+}
+
+/// A set of sensors read together, see [crate::runner::SimRunner::advance_simulation_with_sensors]
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, sensor::SensorArray, runner::{SimRunner, cpu::CPUSimRunner}};
+/// # use futures::executor::block_on;
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.01, 0.01);
+/// let mut world = SimWorldBuilder::new(0.05, 0.01, 0.01)
+///     .with_material(material::COPPER, Box::new(everything.clone()))
+///     .build(0.01);
+/// world.add_heat_source(&AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01), 1.0e7);
+/// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+///
+/// let mut sensors = SensorArray::new();
+/// let heated = sensors.add_sensor(0.005, 0.005, 0.005);
+/// let outside = sensors.add_sensor(1.0, 0.005, 0.005);
+///
+/// // Keep recording across advances
+/// let runner = CPUSimRunner::default();
+/// let timestep = 0.125;
+/// assert!(timestep <= world.max_stable_timestep());
+/// let state = block_on(runner.advance_simulation_with_sensors(&world, &state, 1.25, timestep, &mut sensors)).unwrap();
+/// block_on(runner.advance_simulation_with_sensors(&world, &state, 1.25, timestep, &mut sensors)).unwrap();
+/// assert_eq!(sensors.get_sim_time(), 2.5);
+///
+/// // The heated cell warms at every step under its constant input
+/// let trace = sensors.get_sensors()[heated].get_history();
+/// assert_eq!(trace.len(), 20);
+/// assert!(trace.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+/// assert!(trace[0].1 > 300.0);
+///
+/// assert!(sensors.get_sensors()[outside].get_history().iter().all(|(_, t)| t.is_nan()));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SensorArray {
+    sensors: Vec<Sensor>,
+    sim_time: f64,
+}
+
+impl SensorArray {
+    /// Create a new empty Sensor Array, with a simulated time of 0
+    pub fn new() -> SensorArray {
+        SensorArray::default()
+    }
+
+    /// Add a sensor at a world space point, returning its index in the array
+    pub fn add_sensor(&mut self, x: f64, y: f64, z: f64) -> usize {
+        self.sensors.push(Sensor::new(x, y, z));
+        return self.sensors.len() - 1;
+    }
+
+    /// Get the sensors in the array, in the order they were added
+    pub fn get_sensors(&self) -> &[Sensor] {
+        &self.sensors
+    }
+
+    /// Get the simulated time in seconds of the latest readings, 0 before any are taken
+    pub fn get_sim_time(&self) -> f64 {
+        self.sim_time
+    }
+
+    /// Record the temperature at every sensor in a simulation state, at a simulated time in
+    /// seconds
+    pub fn record<E: Float>(&mut self, world: &SimWorld, sim_state: &SimState<E>, sim_time: f64) {
+        for sensor in self.sensors.iter_mut() {
+            sensor.record(world, sim_state, sim_time);
+        }
+        self.sim_time = sim_time;
+    }
+}
@@ -1,30 +1,104 @@
+use std::collections::HashSet;
+
+use crate::material::Axis;
+
 /// Trait for structs which represent a volume of world space, returns an iterator over tupes of
 /// (x, y, z) positions of cells within the volume
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::{AABBVolume, CellIterator}};
+/// // A 5mm cube brush in a 1cm world with 1mm voxels
+/// let brush = AABBVolume::new(0.003, 0.003, 0.003, 0.008, 0.008, 0.008);
+/// assert_eq!(brush.cell_iter((0.001, 0.001, 0.001)).count(), 125);
+///
+/// let world = SimWorldBuilder::new(0.01, 0.01, 0.01)
+///     .with_material(material::WATER, Box::new(brush))
+///     .build(0.001);
+/// let water_cells = world.get_materials().iter().filter(|m| **m == 1).count();
+/// assert_eq!(water_cells, 125);
+/// ```
 pub trait CellIterator {
-    /// Create an iterator over all positions within a given volume with a given cell size
-    fn cell_iter(self: &Self, cell_size: f32) -> Box<dyn Iterator<Item = (usize, usize, usize)>>;
+    /// Create an iterator over all positions within a given volume with a given (x, y, z) cell
+    /// size
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>>;
+
+    /// Count the number of positions within a given volume with a given (x, y, z) cell size
+    fn cell_count(&self, cell_size: (f64, f64, f64)) -> usize {
+        self.cell_iter(cell_size).count()
+    }
+
+    /// Create an iterator over the positions within a given volume with a given (x, y, z) cell
+    /// size, which are also within a world of the given (x, y, z) size in cells
+    ///
+    /// By default this filters [CellIterator::cell_iter], volumes which can find their cells
+    /// within the bounds directly, such as [AABBVolume], skip the cells outside them entirely.
+    fn clamped_cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        Box::new(
+            self.cell_iter(cell_size)
+                .filter(move |&(x, y, z)| x < bounds.0 && y < bounds.1 && z < bounds.2),
+        )
+    }
+
+    /// Get the world space bounds of the volume, as `(min_x, min_y, min_z, max_x, max_y, max_z)`
+    ///
+    /// Returns None if the volume has no world space bounds, such as volumes defined directly in
+    /// cells. This is the default.
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        None
+    }
+
+    /// Create an iterator over the runs of consecutive cells along x within a given volume with
+    /// a given (x, y, z) cell size, which are also within a world of the given (x, y, z) size in
+    /// cells. Each run is given as its range of x positions and its y and z positions
+    ///
+    /// Worlds are stored x fastest, so a run can be painted as a single slice. By default this
+    /// merges neighboring cells of [CellIterator::clamped_cell_iter] into runs, volumes which can
+    /// find their runs directly, such as [AABBVolume], don't visit the cells one by one.
+    fn clamped_row_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (std::ops::Range<usize>, usize, usize)>> {
+        let mut cells = self.clamped_cell_iter(cell_size, bounds).peekable();
+        Box::new(std::iter::from_fn(move || {
+            let (x, y, z) = cells.next()?;
+            let mut end = x + 1;
+            while cells.next_if(|next| *next == (end, y, z)).is_some() {
+                end += 1;
+            }
+            return Some((x..end, y, z));
+        }))
+    }
 }
 
 /// Struct for representing an axis aligned volume
 #[derive(Debug, Clone)]
 pub struct AABBVolume {
-    min_x: f32,
-    min_y: f32,
-    min_z: f32,
-    max_x: f32,
-    max_y: f32,
-    max_z: f32,
+    min_x: f64,
+    min_y: f64,
+    min_z: f64,
+    max_x: f64,
+    max_y: f64,
+    max_z: f64,
 }
 
 impl AABBVolume {
     /// Create a new AABB Volume
     pub fn new(
-        min_x: f32,
-        min_y: f32,
-        min_z: f32,
-        max_x: f32,
-        max_y: f32,
-        max_z: f32,
+        min_x: f64,
+        min_y: f64,
+        min_z: f64,
+        max_x: f64,
+        max_y: f64,
+        max_z: f64,
     ) -> AABBVolume {
         AABBVolume {
             min_x,
@@ -38,55 +112,1017 @@ impl AABBVolume {
 }
 
 impl CellIterator for AABBVolume {
-    fn cell_iter(self: &Self, cell_size: f32) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
-        return Box::new(AABBVolumeIter {
-            min_x: self.min_x.floor() as usize,
-            min_y: self.min_y.floor() as usize,
-            min_z: self.min_z.floor() as usize,
-            len_x: ((self.max_x - self.min_x) / cell_size).ceil() as usize,
-            len_y: ((self.max_y - self.min_y) / cell_size).ceil() as usize,
-            len_z: ((self.max_z - self.min_z) / cell_size).ceil() as usize,
-            x: 0,
-            y: 0,
-            z: 0,
+    /// Iterates over every cell which overlaps the box
+    ///
+    /// ## Example
+    /// ```
+    /// # use std::collections::HashSet;
+    /// # use thermal_rs::volume::{AABBVolume, CellIterator};
+    /// let cells: Vec<_> = AABBVolume::new(0.0, 0.0, 0.0, 3.0, 3.0, 3.0).cell_iter((1.0, 1.0, 1.0)).collect();
+    /// let unique: HashSet<_> = cells.iter().collect();
+    /// assert_eq!(cells.len(), 27);
+    /// assert_eq!(unique.len(), 27);
+    /// assert!(cells.iter().all(|&(x, y, z)| x < 3 && y < 3 && z < 3));
+    /// ```
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        return Box::new(bounded_cells(
+            (self.min_x, self.min_y, self.min_z),
+            (self.max_x, self.max_y, self.max_z),
+            cell_size,
+        ));
+    }
+
+    /// Counts the cells which overlap the box without iterating over them
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{AABBVolume, CellIterator};
+    /// for size in [0.5, 1.0, 2.5, 3.0] {
+    ///     let aabb = AABBVolume::new(0.2, 0.0, 1.0, 0.2 + size, 2.0 * size, 1.0 + size);
+    ///     assert_eq!(aabb.cell_count((0.5, 0.5, 0.5)), aabb.cell_iter((0.5, 0.5, 0.5)).count());
+    /// }
+    /// ```
+    fn cell_count(&self, cell_size: (f64, f64, f64)) -> usize {
+        cell_range(self.min_x, self.max_x, cell_size.0).len()
+            * cell_range(self.min_y, self.max_y, cell_size.1).len()
+            * cell_range(self.min_z, self.max_z, cell_size.2).len()
+    }
+
+    /// Iterates over every cell which overlaps the box and lies within the bounds, without
+    /// visiting the cells outside them
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{AABBVolume, CellIterator};
+    /// // A brush of 1000 cells, 90% of which fall outside a world 10 cells wide along x
+    /// let aabb = AABBVolume::new(9.0, 0.0, 0.0, 19.0, 10.0, 10.0);
+    /// let cells: Vec<_> = aabb.clamped_cell_iter((1.0, 1.0, 1.0), (10, 10, 10)).collect();
+    /// assert_eq!(cells.len(), 100);
+    /// assert!(cells.iter().all(|&(x, _, _)| x == 9));
+    /// ```
+    fn clamped_cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let clamp = |range: std::ops::Range<usize>, bound: usize| {
+            range.start.min(bound)..range.end.min(bound)
+        };
+        return Box::new(cells_in_ranges(
+            clamp(cell_range(self.min_x, self.max_x, cell_size.0), bounds.0),
+            clamp(cell_range(self.min_y, self.max_y, cell_size.1), bounds.1),
+            clamp(cell_range(self.min_z, self.max_z, cell_size.2), bounds.2),
+        ));
+    }
+
+    /// The corners of the box
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{AABBVolume, CellIterator, UnionVolume};
+    /// let aabb = AABBVolume::new(0.0, 1.0, 2.0, 3.0, 4.0, 5.0);
+    /// assert_eq!(aabb.bounds(), Some((0.0, 1.0, 2.0, 3.0, 4.0, 5.0)));
+    ///
+    /// // Combined volumes enclose the volumes they combine
+    /// let union = UnionVolume::new(Box::new(aabb), Box::new(AABBVolume::new(-1.0, 2.0, 3.0, 2.0, 6.0, 4.0)));
+    /// assert_eq!(union.bounds(), Some((-1.0, 1.0, 2.0, 3.0, 6.0, 5.0)));
+    /// ```
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        Some((
+            self.min_x, self.min_y, self.min_z, self.max_x, self.max_y, self.max_z,
+        ))
+    }
+
+    /// Iterates over every row of cells which overlap the box and lie within the bounds, without
+    /// visiting the cells one by one
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{AABBVolume, CellIterator};
+    /// let aabb = AABBVolume::new(1.0, 0.0, 0.0, 19.0, 2.0, 3.0);
+    /// let rows: Vec<_> = aabb.clamped_row_iter((1.0, 1.0, 1.0), (10, 10, 10)).collect();
+    /// assert_eq!(rows.len(), 2 * 3);
+    /// assert!(rows.iter().all(|(xs, _, _)| *xs == (1..10)));
+    ///
+    /// // The same rows as merging the cells one by one
+    /// let cells = aabb.clamped_cell_iter((1.0, 1.0, 1.0), (10, 10, 10));
+    /// let merged: Vec<_> = rows.iter().flat_map(|(xs, y, z)| xs.clone().map(|x| (x, *y, *z))).collect();
+    /// assert_eq!(merged, cells.collect::<Vec<_>>());
+    /// ```
+    fn clamped_row_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (std::ops::Range<usize>, usize, usize)>> {
+        let clamp = |range: std::ops::Range<usize>, bound: usize| {
+            range.start.min(bound)..range.end.min(bound)
+        };
+        let x_range = clamp(cell_range(self.min_x, self.max_x, cell_size.0), bounds.0);
+        let y_range = clamp(cell_range(self.min_y, self.max_y, cell_size.1), bounds.1);
+        let z_range = clamp(cell_range(self.min_z, self.max_z, cell_size.2), bounds.2);
+        if x_range.is_empty() {
+            return Box::new(std::iter::empty());
+        }
+        return Box::new(z_range.flat_map(move |z| {
+            let x_range = x_range.clone();
+            y_range.clone().map(move |y| (x_range.clone(), y, z))
+        }));
+    }
+}
+
+/// Struct for representing a spherical volume
+#[derive(Debug, Clone)]
+pub struct SphereVolume {
+    center_x: f64,
+    center_y: f64,
+    center_z: f64,
+    radius: f64,
+}
+
+impl SphereVolume {
+    /// Create a new Sphere Volume
+    pub fn new(center_x: f64, center_y: f64, center_z: f64, radius: f64) -> SphereVolume {
+        SphereVolume {
+            center_x,
+            center_y,
+            center_z,
+            radius,
+        }
+    }
+}
+
+impl CellIterator for SphereVolume {
+    /// Iterates over every cell whose center lies within the sphere
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{CellIterator, SphereVolume};
+    /// // A sphere with a radius of 2 cells, centered on a cell
+    /// let sphere = SphereVolume::new(2.5, 2.5, 2.5, 2.0);
+    /// assert_eq!(sphere.cell_iter((1.0, 1.0, 1.0)).count(), 33);
+    /// ```
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (cx, cy, cz, r) = (self.center_x, self.center_y, self.center_z, self.radius);
+        return Box::new(
+            bounded_cells(
+                (cx - r, cy - r, cz - r),
+                (cx + r, cy + r, cz + r),
+                cell_size,
+            )
+            .filter(move |&(x, y, z)| {
+                let dx = cell_center(x, cell_size.0) - cx;
+                let dy = cell_center(y, cell_size.1) - cy;
+                let dz = cell_center(z, cell_size.2) - cz;
+                dx * dx + dy * dy + dz * dz <= r * r
+            }),
+        );
+    }
+
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let (cx, cy, cz, r) = (self.center_x, self.center_y, self.center_z, self.radius);
+        Some((cx - r, cy - r, cz - r, cx + r, cy + r, cz + r))
+    }
+}
+
+/// Struct for representing an axis aligned ellipsoidal volume
+#[derive(Debug, Clone)]
+pub struct EllipsoidVolume {
+    center_x: f64,
+    center_y: f64,
+    center_z: f64,
+    radius_x: f64,
+    radius_y: f64,
+    radius_z: f64,
+}
+
+impl EllipsoidVolume {
+    /// Create a new Ellipsoid Volume, with a separate radius along each axis
+    pub fn new(
+        center_x: f64,
+        center_y: f64,
+        center_z: f64,
+        radius_x: f64,
+        radius_y: f64,
+        radius_z: f64,
+    ) -> EllipsoidVolume {
+        EllipsoidVolume {
+            center_x,
+            center_y,
+            center_z,
+            radius_x,
+            radius_y,
+            radius_z,
+        }
+    }
+}
+
+impl CellIterator for EllipsoidVolume {
+    /// Iterates over every cell whose center lies within the ellipsoid
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{CellIterator, EllipsoidVolume, SphereVolume};
+    /// // With equal radii an ellipsoid is a sphere
+    /// let ellipsoid = EllipsoidVolume::new(2.5, 2.5, 2.5, 2.0, 2.0, 2.0);
+    /// let sphere = SphereVolume::new(2.5, 2.5, 2.5, 2.0);
+    /// let cells: Vec<_> = ellipsoid.cell_iter((1.0, 1.0, 1.0)).collect();
+    /// assert_eq!(cells, sphere.cell_iter((1.0, 1.0, 1.0)).collect::<Vec<_>>());
+    ///
+    /// // Stretched along x, 5 cells along the axis and 4 more around its center
+    /// let stretched = EllipsoidVolume::new(2.5, 2.5, 2.5, 2.0, 1.0, 1.0);
+    /// assert_eq!(stretched.cell_iter((1.0, 1.0, 1.0)).count(), 9);
+    /// ```
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (cx, cy, cz) = (self.center_x, self.center_y, self.center_z);
+        let (rx, ry, rz) = (self.radius_x, self.radius_y, self.radius_z);
+        return Box::new(
+            bounded_cells(
+                (cx - rx, cy - ry, cz - rz),
+                (cx + rx, cy + ry, cz + rz),
+                cell_size,
+            )
+            .filter(move |&(x, y, z)| {
+                let dx = (cell_center(x, cell_size.0) - cx) / rx;
+                let dy = (cell_center(y, cell_size.1) - cy) / ry;
+                let dz = (cell_center(z, cell_size.2) - cz) / rz;
+                dx * dx + dy * dy + dz * dz <= 1.0
+            }),
+        );
+    }
+
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let (cx, cy, cz) = (self.center_x, self.center_y, self.center_z);
+        let (rx, ry, rz) = (self.radius_x, self.radius_y, self.radius_z);
+        Some((cx - rx, cy - ry, cz - rz, cx + rx, cy + ry, cz + rz))
+    }
+}
+
+/// Struct for representing an axis aligned cylindrical volume
+#[derive(Debug, Clone)]
+pub struct CylinderVolume {
+    base_x: f64,
+    base_y: f64,
+    base_z: f64,
+    axis: Axis,
+    radius: f64,
+    height: f64,
+}
+
+impl CylinderVolume {
+    /// Create a new Cylinder Volume, extending from the center of its base along the positive
+    /// direction of the given axis
+    pub fn new(
+        base_x: f64,
+        base_y: f64,
+        base_z: f64,
+        axis: Axis,
+        radius: f64,
+        height: f64,
+    ) -> CylinderVolume {
+        CylinderVolume {
+            base_x,
+            base_y,
+            base_z,
+            axis,
+            radius,
+            height,
+        }
+    }
+}
+
+impl CellIterator for CylinderVolume {
+    /// Iterates over every cell whose center lies within the cylinder
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, material::Axis, volume::CylinderVolume};
+    /// // A rod running through the world which starts and ends outside of it
+    /// let rod = CylinderVolume::new(-1.0, 1.0, 1.0, Axis::X, 0.5, 4.0);
+    /// let world = SimWorldBuilder::new(2.0, 2.0, 2.0)
+    ///     .with_material(material::WATER, Box::new(rod))
+    ///     .build(0.5);
+    /// assert_eq!(world.sample_material(0.1, 1.1, 1.1), Some(&material::WATER));
+    /// assert_eq!(world.sample_material(0.1, 0.1, 0.1), Some(&material::BLANK));
+    /// ```
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (bx, by, bz, r, h) = (
+            self.base_x,
+            self.base_y,
+            self.base_z,
+            self.radius,
+            self.height,
+        );
+        let axis = self.axis;
+        let (min, max) = split_bounds(self.bounds().expect("Cylinders are bounded"));
+        return Box::new(
+            bounded_cells(min, max, cell_size).filter(move |&(x, y, z)| {
+                let dx = cell_center(x, cell_size.0) - bx;
+                let dy = cell_center(y, cell_size.1) - by;
+                let dz = cell_center(z, cell_size.2) - bz;
+                let (along, across_a, across_b) = match axis {
+                    Axis::X => (dx, dy, dz),
+                    Axis::Y => (dy, dx, dz),
+                    Axis::Z => (dz, dx, dy),
+                };
+                (0.0..=h).contains(&along) && across_a * across_a + across_b * across_b <= r * r
+            }),
+        );
+    }
+
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let (bx, by, bz, r, h) = (
+            self.base_x,
+            self.base_y,
+            self.base_z,
+            self.radius,
+            self.height,
+        );
+        return Some(match self.axis {
+            Axis::X => (bx, by - r, bz - r, bx + h, by + r, bz + r),
+            Axis::Y => (bx - r, by, bz - r, bx + r, by + h, bz + r),
+            Axis::Z => (bx - r, by - r, bz, bx + r, by + r, bz + h),
         });
     }
 }
 
-struct AABBVolumeIter {
-    min_x: usize,
-    min_y: usize,
-    min_z: usize,
-    len_x: usize,
-    len_y: usize,
-    len_z: usize,
-    x: usize,
-    y: usize,
-    z: usize,
+/// Struct for representing a cone with its axis along any direction
+#[derive(Debug, Clone)]
+pub struct ConeVolume {
+    apex: (f64, f64, f64),
+    base_center: (f64, f64, f64),
+    base_radius: f64,
 }
 
-impl Iterator for AABBVolumeIter {
-    type Item = (usize, usize, usize);
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.z > self.len_z {
-            return None;
+impl ConeVolume {
+    /// Create a new Cone Volume, narrowing from a circular base to a point at its apex
+    pub fn new(
+        apex: (f64, f64, f64),
+        base_center: (f64, f64, f64),
+        base_radius: f64,
+    ) -> ConeVolume {
+        ConeVolume {
+            apex,
+            base_center,
+            base_radius,
         }
+    }
+}
 
-        self.x += 1;
-        if self.x > self.len_x {
-            self.y += 1;
-            self.x = 0;
+impl CellIterator for ConeVolume {
+    /// Iterates over every cell whose center lies within the cone
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{CellIterator, ConeVolume};
+    /// // A cone 4 cells tall along z, with a radius of 2 cells at its base. Layers from the apex
+    /// // hold 1, 1, 5, 9 and 13 cells
+    /// let cone = ConeVolume::new((2.5, 2.5, 0.5), (2.5, 2.5, 4.5), 2.0);
+    /// assert_eq!(cone.cell_iter((1.0, 1.0, 1.0)).count(), 29);
+    ///
+    /// // The same cone pointing the other way
+    /// let flipped = ConeVolume::new((2.5, 2.5, 4.5), (2.5, 2.5, 0.5), 2.0);
+    /// assert_eq!(flipped.cell_iter((1.0, 1.0, 1.0)).count(), 29);
+    /// ```
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (apex, base, r) = (self.apex, self.base_center, self.base_radius);
+        let (min, max) = split_bounds(self.bounds().expect("Cones are bounded"));
+        return Box::new(
+            bounded_cells(min, max, cell_size).filter(move |&(x, y, z)| {
+                let center = (
+                    cell_center(x, cell_size.0),
+                    cell_center(y, cell_size.1),
+                    cell_center(z, cell_size.2),
+                );
+                let (along, across_sq) = segment_projection(center, apex, base);
+                (0.0..=1.0).contains(&along) && across_sq <= (r * along).powi(2)
+            }),
+        );
+    }
+
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let (apex, base, r) = (self.apex, self.base_center, self.base_radius);
+        // Bound the base as though it were a sphere, then include the apex
+        return Some((
+            apex.0.min(base.0 - r),
+            apex.1.min(base.1 - r),
+            apex.2.min(base.2 - r),
+            apex.0.max(base.0 + r),
+            apex.1.max(base.1 + r),
+            apex.2.max(base.2 + r),
+        ));
+    }
+}
+
+/// Struct for representing a capsule, a cylinder with hemispherical ends, between two points
+/// in any direction
+#[derive(Debug, Clone)]
+pub struct CapsuleVolume {
+    start: (f64, f64, f64),
+    end: (f64, f64, f64),
+    radius: f64,
+}
+
+impl CapsuleVolume {
+    /// Create a new Capsule Volume, containing every point within the radius of the line between
+    /// its two endpoints
+    pub fn new(start: (f64, f64, f64), end: (f64, f64, f64), radius: f64) -> CapsuleVolume {
+        CapsuleVolume { start, end, radius }
+    }
+}
+
+impl CellIterator for CapsuleVolume {
+    /// Iterates over every cell whose center lies within the capsule
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::volume::{CapsuleVolume, CellIterator, SphereVolume};
+    /// // A capsule 2 cells long along x with a radius of 1 cell. The 3 slices between its
+    /// // endpoints hold 5 cells each, and each end cap adds a single cell
+    /// let capsule = CapsuleVolume::new((2.5, 2.5, 2.5), (4.5, 2.5, 2.5), 1.0);
+    /// assert_eq!(capsule.cell_iter((1.0, 1.0, 1.0)).count(), 17);
+    ///
+    /// // With both endpoints together a capsule is a sphere
+    /// let point = CapsuleVolume::new((2.5, 2.5, 2.5), (2.5, 2.5, 2.5), 2.0);
+    /// assert_eq!(
+    ///     point.cell_iter((1.0, 1.0, 1.0)).count(),
+    ///     SphereVolume::new(2.5, 2.5, 2.5, 2.0).cell_iter((1.0, 1.0, 1.0)).count()
+    /// );
+    /// ```
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (start, end, r) = (self.start, self.end, self.radius);
+        let (min, max) = split_bounds(self.bounds().expect("Capsules are bounded"));
+        return Box::new(
+            bounded_cells(min, max, cell_size).filter(move |&(x, y, z)| {
+                let center = (
+                    cell_center(x, cell_size.0),
+                    cell_center(y, cell_size.1),
+                    cell_center(z, cell_size.2),
+                );
+                // Distance to the nearest point on the line between the endpoints
+                let (along, across_sq) = segment_projection(center, start, end);
+                let clamped = along.clamp(0.0, 1.0);
+                let distance_sq = across_sq + (along - clamped).powi(2) * distance_sq(start, end);
+                distance_sq <= r * r
+            }),
+        );
+    }
+
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let (start, end, r) = (self.start, self.end, self.radius);
+        return Some((
+            start.0.min(end.0) - r,
+            start.1.min(end.1) - r,
+            start.2.min(end.2) - r,
+            start.0.max(end.0) + r,
+            start.1.max(end.1) + r,
+            start.2.max(end.2) + r,
+        ));
+    }
+}
+
+/// Struct for representing every point on one side of a plane, within the bounds of a world
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::volume::{AABBVolume, CellIterator, HalfSpaceVolume, IntersectionVolume};
+/// // An axis aligned plane through the middle of a 4x4x4 world fills half of it
+/// let world_bounds = AABBVolume::new(0.0, 0.0, 0.0, 4.0, 4.0, 4.0);
+/// let half = HalfSpaceVolume::new((2.0, 0.0, 0.0), (1.0, 0.0, 0.0), world_bounds.clone());
+/// assert_eq!(half.cell_iter((1.0, 1.0, 1.0)).count(), 32);
+/// assert!(half.cell_iter((1.0, 1.0, 1.0)).all(|(x, _, _)| x >= 2));
+///
+/// // Clipped to a box with an intersection
+/// let clipped = IntersectionVolume::new(
+///     Box::new(half),
+///     Box::new(AABBVolume::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0)),
+/// );
+/// assert_eq!(clipped.cell_iter((1.0, 1.0, 1.0)).count(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct HalfSpaceVolume {
+    point: (f64, f64, f64),
+    normal: (f64, f64, f64),
+    bounds: AABBVolume,
+}
+
+impl HalfSpaceVolume {
+    /// Create a new Half Space Volume, containing the cells within the bounds whose center lies
+    /// on the plane through the point, or on the side of it the normal points towards. The
+    /// normal does not need to be of unit length
+    pub fn new(
+        point: (f64, f64, f64),
+        normal: (f64, f64, f64),
+        bounds: AABBVolume,
+    ) -> HalfSpaceVolume {
+        HalfSpaceVolume {
+            point,
+            normal,
+            bounds,
         }
+    }
+}
+
+impl CellIterator for HalfSpaceVolume {
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (point, normal) = (self.point, self.normal);
+        return Box::new(self.bounds.cell_iter(cell_size).filter(move |&(x, y, z)| {
+            let dx = cell_center(x, cell_size.0) - point.0;
+            let dy = cell_center(y, cell_size.1) - point.1;
+            let dz = cell_center(z, cell_size.2) - point.2;
+            dx * normal.0 + dy * normal.1 + dz * normal.2 >= 0.0
+        }));
+    }
+
+    fn clamped_cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (point, normal) = (self.point, self.normal);
+        return Box::new(self.bounds.clamped_cell_iter(cell_size, bounds).filter(
+            move |&(x, y, z)| {
+                let dx = cell_center(x, cell_size.0) - point.0;
+                let dy = cell_center(y, cell_size.1) - point.1;
+                let dz = cell_center(z, cell_size.2) - point.2;
+                dx * normal.0 + dy * normal.1 + dz * normal.2 >= 0.0
+            },
+        ));
+    }
+
+    /// The bounds the half space is limited to
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        self.bounds.bounds()
+    }
+}
+
+/// Struct for representing an arbitrary volume defined by a predicate
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::volume::{AABBVolume, CellIterator, FnVolume, SphereVolume};
+/// let sphere = FnVolume::new(
+///     AABBVolume::new(0.0, 0.0, 0.0, 5.0, 5.0, 5.0),
+///     Box::new(|x, y, z| (x - 2.5).powi(2) + (y - 2.5).powi(2) + (z - 2.5).powi(2) <= 4.0),
+/// );
+/// assert_eq!(
+///     sphere.cell_iter((1.0, 1.0, 1.0)).count(),
+///     SphereVolume::new(2.5, 2.5, 2.5, 2.0).cell_iter((1.0, 1.0, 1.0)).count()
+/// );
+/// ```
+pub struct FnVolume {
+    bounds: AABBVolume,
+    predicate: Box<dyn Fn(f64, f64, f64) -> bool>,
+}
+
+impl FnVolume {
+    /// Create a new Fn Volume, containing the cells within the bounds whose world space center
+    /// satisfies the predicate
+    pub fn new(bounds: AABBVolume, predicate: Box<dyn Fn(f64, f64, f64) -> bool>) -> FnVolume {
+        FnVolume { bounds, predicate }
+    }
+}
 
-        if self.y > self.len_y {
-            self.z += 1;
-            self.y = 0;
+impl CellIterator for FnVolume {
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        // The predicate is borrowed from the volume, so cells must be evaluated up front
+        let cells: Vec<(usize, usize, usize)> = self
+            .bounds
+            .cell_iter(cell_size)
+            .filter(|&(x, y, z)| {
+                (self.predicate)(
+                    cell_center(x, cell_size.0),
+                    cell_center(y, cell_size.1),
+                    cell_center(z, cell_size.2),
+                )
+            })
+            .collect();
+        return Box::new(cells.into_iter());
+    }
+
+    /// The bounds the predicate is evaluated within
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        self.bounds.bounds()
+    }
+}
+
+/// Possible errors when creating volumes
+#[derive(Debug)]
+pub enum VolumeError {
+    /// The length of a bitmap does not match the dimensions given for it
+    BitmapSizeMismatch,
+}
+
+/// Struct for representing a volume from a 3d bitmap of cells
+///
+/// Bitmaps are defined directly in cells rather than in world space, so this volume ignores the
+/// cell size it is iterated with.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::volume::{BitmapVolume, CellIterator};
+/// // A 2x2x1 bitmap with two set cells, placed at (5, 5, 0)
+/// let bitmap = BitmapVolume::new(vec![true, false, false, true], 2, 2, 1, (5, 5, 0)).unwrap();
+/// let cells: Vec<_> = bitmap.cell_iter((0.1, 0.1, 0.1)).collect();
+/// assert_eq!(cells, vec![(5, 5, 0), (6, 6, 0)]);
+///
+/// assert!(BitmapVolume::new(vec![true; 3], 2, 2, 1, (0, 0, 0)).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct BitmapVolume {
+    data: Vec<bool>,
+    dim_x: usize,
+    dim_y: usize,
+    dim_z: usize,
+    offset: (usize, usize, usize),
+}
+
+impl BitmapVolume {
+    /// Create a new Bitmap Volume from a buffer of cells ordered x first, then y, then z, and the
+    /// cell position of its first cell. Fails if the buffer length does not match the dimensions
+    pub fn new(
+        data: Vec<bool>,
+        dim_x: usize,
+        dim_y: usize,
+        dim_z: usize,
+        offset: (usize, usize, usize),
+    ) -> Result<BitmapVolume, VolumeError> {
+        if data.len() != dim_x * dim_y * dim_z {
+            return Err(VolumeError::BitmapSizeMismatch);
         }
+        return Ok(BitmapVolume {
+            data,
+            dim_x,
+            dim_y,
+            dim_z,
+            offset,
+        });
+    }
+}
+
+impl CellIterator for BitmapVolume {
+    fn cell_iter(
+        &self,
+        _cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let (dim_x, dim_y) = (self.dim_x, self.dim_y);
+        let (off_x, off_y, off_z) = self.offset;
+        let cells: Vec<(usize, usize, usize)> = self
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| **set)
+            .map(|(i, _)| {
+                (
+                    off_x + i % dim_x,
+                    off_y + (i / dim_x) % dim_y,
+                    off_z + i / (dim_x * dim_y),
+                )
+            })
+            .collect();
+        return Box::new(cells.into_iter());
+    }
+}
+
+/// Struct for representing the union of two volumes
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::volume::{CellIterator, SphereVolume, UnionVolume, IntersectionVolume, DifferenceVolume};
+/// // Two spheres of 33 cells each, offset by one cell
+/// let a = || Box::new(SphereVolume::new(2.5, 2.5, 2.5, 2.0));
+/// let b = || Box::new(SphereVolume::new(3.5, 2.5, 2.5, 2.0));
+/// assert_eq!(UnionVolume::new(a(), b()).cell_iter((1.0, 1.0, 1.0)).count(), 46);
+/// assert_eq!(IntersectionVolume::new(a(), b()).cell_iter((1.0, 1.0, 1.0)).count(), 20);
+/// assert_eq!(DifferenceVolume::new(a(), b()).cell_iter((1.0, 1.0, 1.0)).count(), 13);
+/// ```
+pub struct UnionVolume {
+    a: Box<dyn CellIterator>,
+    b: Box<dyn CellIterator>,
+}
+
+impl UnionVolume {
+    /// Create a new Union Volume, containing the cells of either volume
+    pub fn new(a: Box<dyn CellIterator>, b: Box<dyn CellIterator>) -> UnionVolume {
+        UnionVolume { a, b }
+    }
+}
 
+impl CellIterator for UnionVolume {
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let mut seen: HashSet<(usize, usize, usize)> = HashSet::new();
+        return Box::new(
+            self.a
+                .cell_iter(cell_size)
+                .chain(self.b.cell_iter(cell_size))
+                .filter(move |cell| seen.insert(*cell)),
+        );
+    }
+
+    fn clamped_cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let mut seen: HashSet<(usize, usize, usize)> = HashSet::new();
+        return Box::new(
+            self.a
+                .clamped_cell_iter(cell_size, bounds)
+                .chain(self.b.clamped_cell_iter(cell_size, bounds))
+                .filter(move |cell| seen.insert(*cell)),
+        );
+    }
+
+    /// The box enclosing both volumes, None if either is unbounded
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        let (a, b) = (self.a.bounds()?, self.b.bounds()?);
         return Some((
-            self.min_x + self.x,
-            self.min_y + self.y,
-            self.min_z + self.z,
+            a.0.min(b.0),
+            a.1.min(b.1),
+            a.2.min(b.2),
+            a.3.max(b.3),
+            a.4.max(b.4),
+            a.5.max(b.5),
         ));
     }
 }
+
+/// Struct for representing the intersection of two volumes
+pub struct IntersectionVolume {
+    a: Box<dyn CellIterator>,
+    b: Box<dyn CellIterator>,
+}
+
+impl IntersectionVolume {
+    /// Create a new Intersection Volume, containing the cells present in both volumes
+    pub fn new(a: Box<dyn CellIterator>, b: Box<dyn CellIterator>) -> IntersectionVolume {
+        IntersectionVolume { a, b }
+    }
+}
+
+impl CellIterator for IntersectionVolume {
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let b_cells: HashSet<(usize, usize, usize)> = self.b.cell_iter(cell_size).collect();
+        let mut seen: HashSet<(usize, usize, usize)> = HashSet::new();
+        return Box::new(
+            self.a
+                .cell_iter(cell_size)
+                .filter(move |cell| b_cells.contains(cell) && seen.insert(*cell)),
+        );
+    }
+
+    fn clamped_cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let b_cells: HashSet<(usize, usize, usize)> =
+            self.b.clamped_cell_iter(cell_size, bounds).collect();
+        let mut seen: HashSet<(usize, usize, usize)> = HashSet::new();
+        return Box::new(
+            self.a
+                .clamped_cell_iter(cell_size, bounds)
+                .filter(move |cell| b_cells.contains(cell) && seen.insert(*cell)),
+        );
+    }
+
+    /// The overlap of the bounds of both volumes, or the bounds of whichever volume is bounded
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        return match (self.a.bounds(), self.b.bounds()) {
+            (Some(a), Some(b)) => Some((
+                a.0.max(b.0),
+                a.1.max(b.1),
+                a.2.max(b.2),
+                a.3.min(b.3),
+                a.4.min(b.4),
+                a.5.min(b.5),
+            )),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// Struct for representing the difference of two volumes
+pub struct DifferenceVolume {
+    a: Box<dyn CellIterator>,
+    b: Box<dyn CellIterator>,
+}
+
+impl DifferenceVolume {
+    /// Create a new Difference Volume, containing the cells of volume a which are not in volume b
+    pub fn new(a: Box<dyn CellIterator>, b: Box<dyn CellIterator>) -> DifferenceVolume {
+        DifferenceVolume { a, b }
+    }
+}
+
+impl CellIterator for DifferenceVolume {
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let b_cells: HashSet<(usize, usize, usize)> = self.b.cell_iter(cell_size).collect();
+        let mut seen: HashSet<(usize, usize, usize)> = HashSet::new();
+        return Box::new(
+            self.a
+                .cell_iter(cell_size)
+                .filter(move |cell| !b_cells.contains(cell) && seen.insert(*cell)),
+        );
+    }
+
+    fn clamped_cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+        bounds: (usize, usize, usize),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let b_cells: HashSet<(usize, usize, usize)> =
+            self.b.clamped_cell_iter(cell_size, bounds).collect();
+        let mut seen: HashSet<(usize, usize, usize)> = HashSet::new();
+        return Box::new(
+            self.a
+                .clamped_cell_iter(cell_size, bounds)
+                .filter(move |cell| !b_cells.contains(cell) && seen.insert(*cell)),
+        );
+    }
+
+    /// The bounds of volume a, which the difference lies within
+    fn bounds(&self) -> Option<(f64, f64, f64, f64, f64, f64)> {
+        self.a.bounds()
+    }
+}
+
+/// Struct for representing a volume translated and scaled in cell space
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::volume::{AABBVolume, CellIterator, TransformedVolume};
+/// let aabb = || Box::new(AABBVolume::new(0.0, 0.0, 0.0, 2.0, 2.0, 2.0));
+/// let moved = TransformedVolume::new(aabb(), (5, 0, 0), 1);
+/// assert!(moved.cell_iter((1.0, 1.0, 1.0)).zip(aabb().cell_iter((1.0, 1.0, 1.0))).all(|(m, o)| m.0 == o.0 + 5));
+///
+/// let scaled = TransformedVolume::new(aabb(), (0, 0, 0), 2);
+/// assert_eq!(scaled.cell_count((1.0, 1.0, 1.0)), 64);
+/// ```
+pub struct TransformedVolume {
+    inner: Box<dyn CellIterator>,
+    translation: (i64, i64, i64),
+    scale: usize,
+}
+
+impl TransformedVolume {
+    /// Create a new Transformed Volume, scaling every cell of the inner volume up into a cube of
+    /// `scale` cells per side, then translating it by a whole number of cells. Cells translated
+    /// to negative positions are dropped
+    pub fn new(
+        inner: Box<dyn CellIterator>,
+        translation: (i64, i64, i64),
+        scale: usize,
+    ) -> TransformedVolume {
+        TransformedVolume {
+            inner,
+            translation,
+            scale,
+        }
+    }
+}
+
+impl CellIterator for TransformedVolume {
+    fn cell_iter(
+        &self,
+        cell_size: (f64, f64, f64),
+    ) -> Box<dyn Iterator<Item = (usize, usize, usize)>> {
+        let scale = self.scale;
+        let (tx, ty, tz) = self.translation;
+        return Box::new(
+            self.inner
+                .cell_iter(cell_size)
+                .flat_map(move |(x, y, z)| {
+                    (0..scale).flat_map(move |dz| {
+                        (0..scale).flat_map(move |dy| {
+                            (0..scale).map(move |dx| {
+                                (
+                                    (x * scale + dx) as i64 + tx,
+                                    (y * scale + dy) as i64 + ty,
+                                    (z * scale + dz) as i64 + tz,
+                                )
+                            })
+                        })
+                    })
+                })
+                .filter_map(|(x, y, z)| {
+                    Some((
+                        usize::try_from(x).ok()?,
+                        usize::try_from(y).ok()?,
+                        usize::try_from(z).ok()?,
+                    ))
+                }),
+        );
+    }
+}
+
+/// Project a point onto the line through two points, returning the fraction of the way from
+/// `start` to `end` of the projected point, and the squared distance of the point from the line.
+/// If the two points are the same the point is projected onto `start`
+fn segment_projection(
+    point: (f64, f64, f64),
+    start: (f64, f64, f64),
+    end: (f64, f64, f64),
+) -> (f64, f64) {
+    let line = (end.0 - start.0, end.1 - start.1, end.2 - start.2);
+    let offset = (point.0 - start.0, point.1 - start.1, point.2 - start.2);
+    let length_sq = distance_sq(start, end);
+    if length_sq == 0.0 {
+        return (0.0, distance_sq(start, point));
+    }
+    let dot = offset.0 * line.0 + offset.1 * line.1 + offset.2 * line.2;
+    let along = dot / length_sq;
+    return (along, (distance_sq(start, point) - dot * along).max(0.0));
+}
+
+/// Get the squared distance between two points
+fn distance_sq(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    (b.0 - a.0).powi(2) + (b.1 - a.1).powi(2) + (b.2 - a.2).powi(2)
+}
+
+/// Split world space bounds into their minimum and maximum corners
+fn split_bounds(
+    (min_x, min_y, min_z, max_x, max_y, max_z): (f64, f64, f64, f64, f64, f64),
+) -> ((f64, f64, f64), (f64, f64, f64)) {
+    ((min_x, min_y, min_z), (max_x, max_y, max_z))
+}
+
+/// Iterate over every cell which overlaps the box between the given world space corners
+fn bounded_cells(
+    min: (f64, f64, f64),
+    max: (f64, f64, f64),
+    cell_size: (f64, f64, f64),
+) -> impl Iterator<Item = (usize, usize, usize)> {
+    cells_in_ranges(
+        cell_range(min.0, max.0, cell_size.0),
+        cell_range(min.1, max.1, cell_size.1),
+        cell_range(min.2, max.2, cell_size.2),
+    )
+}
+
+/// Iterate over every cell within the given ranges of cells along each axis
+fn cells_in_ranges(
+    x_range: std::ops::Range<usize>,
+    y_range: std::ops::Range<usize>,
+    z_range: std::ops::Range<usize>,
+) -> impl Iterator<Item = (usize, usize, usize)> {
+    z_range.flat_map(move |z| {
+        let x_range = x_range.clone();
+        y_range
+            .clone()
+            .flat_map(move |y| x_range.clone().map(move |x| (x, y, z)))
+    })
+}
+
+/// Get the range of cells along an axis which overlap the given world space interval
+fn cell_range(min: f64, max: f64, cell_size: f64) -> std::ops::Range<usize> {
+    snap_to_cell(min / cell_size).floor() as usize..snap_to_cell(max / cell_size).ceil() as usize
+}
+
+/// Snap a position in cell units to the nearest cell boundary when it is only off by rounding
+/// error, so that brushes aligned with cell boundaries don't gain an extra row of cells
+fn snap_to_cell(position: f64) -> f64 {
+    let boundary = position.round();
+    if (position - boundary).abs() < 1e-9 * boundary.abs().max(1.0) {
+        boundary
+    } else {
+        position
+    }
+}
+
+/// Get the world space position of the center of a cell along an axis
+fn cell_center(index: usize, cell_size: f64) -> f64 {
+    (index as f64 + 0.5) * cell_size
+}
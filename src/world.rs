@@ -1,8 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::{
-    material::{self, Material},
-    volume::CellIterator,
+    import::{CellList, VoxError, read_vox},
+    material::{self, Axis, Material},
+    volume::{AABBVolume, CellIterator, SphereVolume},
 };
 
 /// A builder for simulation worlds
@@ -18,95 +19,787 @@ use crate::{
 ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 0.5, 1.0)))
 ///     .build(0.1);
 /// ```
-#[derive(Default)]
 pub struct SimWorldBuilder {
-    x_size: f32,
-    y_size: f32,
-    z_size: f32,
-    brush_opperations: Vec<(Material, Box<dyn CellIterator>)>,
+    x_size: f64,
+    y_size: f64,
+    z_size: f64,
+    brush_opperations: Vec<(i32, Material, Box<dyn CellIterator>)>,
+    boundaries: [WorldBoundary; 3],
+    default_material: Material,
+    ambient_temperature: f32,
+    planar: bool,
+    expected_materials: usize,
+    auto_size: bool,
+    margin: f64,
+}
+
+impl Default for SimWorldBuilder {
+    fn default() -> Self {
+        return SimWorldBuilder::new(0.0, 0.0, 0.0);
+    }
 }
 
 impl SimWorldBuilder {
     /// Create a new builder, defining the dimensions of the world to be built
-    pub fn new(x_size: f32, y_size: f32, z_size: f32) -> Self {
+    pub fn new(x_size: f64, y_size: f64, z_size: f64) -> Self {
         SimWorldBuilder {
             x_size,
             y_size,
             z_size,
             brush_opperations: Vec::new(),
+            boundaries: [WorldBoundary::Insulated; 3],
+            default_material: material::BLANK,
+            ambient_temperature: 293.15,
+            planar: false,
+            expected_materials: 0,
+            auto_size: false,
+            margin: 0.0,
         }
     }
 
+    /// Create a new builder for a planar world, a single cell deep along z, for plates and
+    /// cross sections
+    ///
+    /// Cells keep the z size they are built with, so energies are those of a plate one cell
+    /// thick. Brushes must overlap the layer of cells between z = 0 and the z cell size. No heat
+    /// flows along z, as the faces of the world are insulated by default.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}, export::isosurface_obj};
+    /// # use futures::executor::block_on;
+    /// let plate = AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.05, 0.01);
+    /// let world = SimWorldBuilder::new_2d(0.05, 0.05)
+    ///     .with_material(material::COPPER, Box::new(plate.clone()))
+    ///     .build(0.01);
+    /// assert_eq!((world.get_x_size(), world.get_y_size(), world.get_z_size()), (5, 5, 1));
+    /// assert!(world.is_planar());
+    ///
+    /// // A hot spot in the center of the plate
+    /// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &plate).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.02, 0.02, 0.0, 0.03, 0.03, 0.01)).unwrap();
+    /// let result = block_on(CPUSimRunner::default().advance_simulation(&world, &state, 1.0, world.max_stable_timestep())).unwrap();
+    /// world.assert_energy_conserved(&state, &result, 1e-6);
+    ///
+    /// // Heat spreads evenly in every direction within the plane
+    /// let temperature = |x, y| world.sample_voxel_temperature(&result, x, y, 0).unwrap();
+    /// let side = temperature(1, 2);
+    /// for (x, y) in [(3, 2), (2, 1), (2, 3)] {
+    ///     assert!((temperature(x, y) - side).abs() < 0.01);
+    /// }
+    /// assert!(temperature(2, 2) > side && side > temperature(1, 1) && temperature(1, 1) > temperature(0, 0));
+    ///
+    /// // Isosurfaces of a plane are contour lines
+    /// let path = std::env::temp_dir().join("thermal_rs_doctest_contour.obj");
+    /// isosurface_obj(&world, &result, (side + temperature(2, 2)) / 2.0, &path).unwrap();
+    /// let contents = std::fs::read_to_string(&path).unwrap();
+    /// assert!(contents.lines().any(|line| line.starts_with("l ")));
+    /// assert!(!contents.lines().any(|line| line.starts_with("f ")));
+    /// ```
+    pub fn new_2d(x_size: f64, y_size: f64) -> Self {
+        let mut builder = SimWorldBuilder::new(x_size, y_size, 0.0);
+        builder.planar = true;
+        return builder;
+    }
+
+    /// Create a new builder which sizes the world to fit its brushes when it is built, from the
+    /// origin to the furthest extent of any brush along each axis plus a margin
+    ///
+    /// Building fails with [BuildError::UnboundedBrush] if any brush has no world space bounds,
+    /// see [CellIterator::bounds], as the world could not contain it.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{BuildError, SimWorldBuilder}, material, volume::{BitmapVolume, SphereVolume}};
+    /// let world = SimWorldBuilder::new_auto()
+    ///     .with_material(material::COPPER, Box::new(SphereVolume::new(5.0, 5.0, 5.0, 1.0)))
+    ///     .with_margin(0.5)
+    ///     .build(0.5);
+    /// assert_eq!((world.get_x_size(), world.get_y_size(), world.get_z_size()), (13, 13, 13));
+    /// assert_eq!(world.sample_material(5.0, 5.0, 5.0), Some(&material::COPPER));
+    ///
+    /// let bitmap = BitmapVolume::new(vec![true], 1, 1, 1, (0, 0, 0)).unwrap();
+    /// let unbounded = SimWorldBuilder::new_auto().with_material(material::COPPER, Box::new(bitmap)).try_build(0.5);
+    /// assert!(matches!(unbounded, Err(BuildError::UnboundedBrush)));
+    /// ```
+    pub fn new_auto() -> Self {
+        let mut builder = SimWorldBuilder::new(0.0, 0.0, 0.0);
+        builder.auto_size = true;
+        return builder;
+    }
+
+    /// Sets the space in m left beyond the furthest brush along each axis by worlds sized to fit
+    /// their brushes, see [SimWorldBuilder::new_auto], this is 0 by default
+    pub fn with_margin(mut self, margin: f64) -> Self {
+        self.margin = margin;
+        return self;
+    }
+
     /// Applies a material type to a volume defined by a brush
-    pub fn with_material(mut self, material: Material, brush: Box<dyn CellIterator>) -> Self {
-        self.brush_opperations.push((material, brush));
+    ///
+    /// Brushes applied this way have a priority of 0, see [SimWorldBuilder::with_material_priority]
+    pub fn with_material(self, material: Material, brush: Box<dyn CellIterator>) -> Self {
+        return self.with_material_priority(material, brush, 0);
+    }
+
+    /// Applies a material type to a volume defined by a brush, with a priority used to decide
+    /// which material wins where brushes overlap
+    ///
+    /// Brushes are applied in ascending priority, so higher priority brushes overwrite lower
+    /// priority ones. Brushes with the same priority are applied in the order they were added.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(4.0, 4.0, 4.0)
+    ///     .with_material_priority(material::COPPER, Box::new(AABBVolume::new(1.0, 1.0, 1.0, 2.0, 2.0, 2.0)), 1)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 4.0, 4.0, 4.0)))
+    ///     .build(1.0);
+    /// assert_eq!(world.sample_material(1.5, 1.5, 1.5), Some(&material::COPPER));
+    /// assert_eq!(world.sample_material(3.5, 3.5, 3.5), Some(&material::WATER));
+    /// ```
+    pub fn with_material_priority(
+        mut self,
+        material: Material,
+        brush: Box<dyn CellIterator>,
+        priority: i32,
+    ) -> Self {
+        self.brush_opperations.push((priority, material, brush));
         return self;
     }
 
-    /// Build the world with a given voxel resolution
-    pub fn build(self, resolution: f32) -> SimWorld {
-        // Get x y and z size of world in voxels
-        let world_x = (self.x_size / resolution).ceil() as usize;
-        let world_y = (self.y_size / resolution).ceil() as usize;
-        let world_z = (self.z_size / resolution).ceil() as usize;
+    /// Size the world to fit every brush added so far, from the origin to the furthest extent of
+    /// any brush along each axis
+    ///
+    /// Brushes without world space bounds, see [CellIterator::bounds], are ignored, and sizes are
+    /// left unchanged if no brush has bounds. Planar worlds keep their single layer of cells.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::{AABBVolume, SphereVolume}};
+    /// let world = SimWorldBuilder::default()
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+    ///     .with_material(material::COPPER, Box::new(SphereVolume::new(1.0, 2.0, 1.0, 1.0)))
+    ///     .auto_size()
+    ///     .build(0.5);
+    /// assert_eq!((world.get_x_size(), world.get_y_size(), world.get_z_size()), (4, 6, 4));
+    /// ```
+    pub fn auto_size(mut self) -> Self {
+        let extents = self
+            .brush_opperations
+            .iter()
+            .filter_map(|(_, _, brush)| brush.bounds())
+            .map(|(_, _, _, max_x, max_y, max_z)| (max_x, max_y, max_z))
+            .reduce(|a, b| (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)));
+        if let Some(extents) = extents {
+            self.fit_to(extents);
+        }
+        return self;
+    }
 
-        let pos_to_index = |x: usize, y: usize, z: usize| {
-            if x < world_x && y < world_y && z < world_z {
-                Some(x + y * world_x + z * world_x * world_y)
-            } else {
-                None
-            }
-        };
+    /// Size the world from the origin to the given (x, y, z) extents, keeping the single layer of
+    /// cells of planar worlds
+    fn fit_to(&mut self, (x_size, y_size, z_size): (f64, f64, f64)) {
+        self.x_size = x_size.max(0.0);
+        self.y_size = y_size.max(0.0);
+        if !self.planar {
+            self.z_size = z_size.max(0.0);
+        }
+    }
 
-        // Create new material buffer
-        let mut material_buffer: Vec<u8> = Vec::new();
-        material_buffer.resize(world_x * world_y * world_z, 0);
+    /// Size a world created with [SimWorldBuilder::new_auto] to fit its brushes and margin, fails
+    /// if any brush is unbounded. Worlds with fixed sizes are unchanged
+    fn resolve_auto_size(&mut self) -> Result<(), BuildError> {
+        if !self.auto_size {
+            return Ok(());
+        }
+        let mut extents = (0.0, 0.0, 0.0);
+        for (_, _, brush) in self.brush_opperations.iter() {
+            let (_, _, _, max_x, max_y, max_z) =
+                brush.bounds().ok_or(BuildError::UnboundedBrush)?;
+            extents = (
+                f64::max(extents.0, max_x),
+                f64::max(extents.1, max_y),
+                f64::max(extents.2, max_z),
+            );
+        }
+        let margin = self.margin;
+        self.fit_to((extents.0 + margin, extents.1 + margin, extents.2 + margin));
+        self.auto_size = false;
+        return Ok(());
+    }
 
-        // Create material map
-        let mut material_map: HashMap<Material, u8> = HashMap::from([(material::BLANK, 0)]);
+    /// Preallocate space for the given number of brushes, to avoid reallocating while adding
+    /// thousands of them
+    ///
+    /// This is only a hint, more or fewer brushes may still be added and the built world is the
+    /// same either way.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume};
+    /// let build = |hint: Option<usize>| {
+    ///     let mut builder = SimWorldBuilder::new(1.0, 1.0, 1.0);
+    ///     if let Some(hint) = hint {
+    ///         builder = builder.with_expected_brushes(hint).with_expected_materials(hint);
+    ///     }
+    ///     for i in 0..100 {
+    ///         let material = Material { density: 1000.0 + (i % 10) as f32, ..material::WATER };
+    ///         let corner = (i % 10) as f64 / 10.0;
+    ///         builder = builder.with_material(material, Box::new(AABBVolume::new(corner, 0.0, 0.0, corner + 0.1, 1.0, 1.0)));
+    ///     }
+    ///     return builder.build(0.1);
+    /// };
+    /// let unhinted = build(None);
+    /// for hint in [1, 10, 1000] {
+    ///     let hinted = build(Some(hint));
+    ///     assert_eq!(hinted.get_material_map(), unhinted.get_material_map());
+    ///     assert_eq!(hinted.get_materials(), unhinted.get_materials());
+    /// }
+    /// ```
+    pub fn with_expected_brushes(mut self, brushes: usize) -> Self {
+        self.brush_opperations.reserve(brushes);
+        return self;
+    }
 
-        // Write brushes into buffer
-        for (mat, brush) in self.brush_opperations.iter() {
-            let index: u8 = match material_map.get(mat) {
-                Some(i) => *i as u8,
-                None => {
-                    let new_index = material_map.len();
-                    material_map.insert(mat.clone(), new_index as u8);
-                    assert!(
-                        new_index <= u8::MAX as usize,
-                        "There can be at most 256 distinct materials present in a simulation."
-                    );
-                    new_index as u8
-                }
-            };
+    /// Preallocate space in the built world for the given number of distinct materials,
+    /// including the default material
+    ///
+    /// This is only a hint, as with [SimWorldBuilder::with_expected_brushes].
+    pub fn with_expected_materials(mut self, materials: usize) -> Self {
+        self.expected_materials = materials;
+        return self;
+    }
 
-            for (x, y, z) in brush.cell_iter(resolution) {
-                if let Some(i) = pos_to_index(x, y, z) {
-                    if let Some(v) = material_buffer.get_mut(i) {
-                        *v = index;
-                    }
-                }
-            }
+    /// Sets the material that fills every cell not painted by a brush, this is [material::BLANK]
+    /// by default
+    ///
+    /// The default material always has index 0 in the material map.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(2.0, 1.0, 1.0)
+    ///     .with_default_material(material::AIR)
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(1.0);
+    /// assert_eq!(world.sample_material(1.5, 0.5, 0.5), Some(&material::AIR));
+    /// assert_eq!(world.get_material_map()[0], material::AIR);
+    /// ```
+    pub fn with_default_material(mut self, material: Material) -> Self {
+        self.default_material = material;
+        return self;
+    }
+
+    /// Sets the boundary condition at both faces of the world along an axis, boundaries are
+    /// [WorldBoundary::Insulated] by default
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{SimWorldBuilder, WorldBoundary}, material::{self, Axis}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// let bar = AABBVolume::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0);
+    /// let hot_spot = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+    /// let world = SimWorldBuilder::new(4.0, 1.0, 1.0)
+    ///     .with_material(material::COPPER, Box::new(bar.clone()))
+    ///     .with_boundary(Axis::X, WorldBoundary::Periodic)
+    ///     .build(1.0);
+    /// let mut state = world.get_blank_sim_state();
+    /// state = world.set_sim_state_temperature(state, 300.0, &bar).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &hot_spot).unwrap();
+    ///
+    /// // Heat from the first cell wraps around into the last cell
+    /// let result = CPUSimRunner::default().advance_step(&world, state, 100.0);
+    /// assert!(world.sample_voxel_temperature(&result, 3, 0, 0).unwrap() > 300.5);
+    /// assert!((world.sample_voxel_temperature(&result, 2, 0, 0).unwrap() - 300.0).abs() < 0.01);
+    /// ```
+    pub fn with_boundary(mut self, axis: Axis, boundary: WorldBoundary) -> Self {
+        self.boundaries[axis as usize] = boundary;
+        return self;
+    }
+
+    /// Sets the temperature in kelvin of the fluid surrounding the world, 293.15 by default
+    ///
+    /// Cells of the default material are treated as surrounded by this fluid, so faces between
+    /// any other material and the default material lose heat by convection at a rate set by the
+    /// material's [Material::convective_coefficient].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// // A hot copper block surrounded by air
+    /// let block = AABBVolume::new(0.01, 0.01, 0.01, 0.02, 0.02, 0.02);
+    /// let cooled_temperature = |convective_coefficient: f32| {
+    ///     let copper = Material { convective_coefficient, ..material::COPPER };
+    ///     let world = SimWorldBuilder::new(0.03, 0.03, 0.03)
+    ///         .with_material(copper, Box::new(block.clone()))
+    ///         .with_ambient_temperature(300.0)
+    ///         .build(0.01);
+    ///     let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 400.0, &block).unwrap();
+    ///     let result = block_on(CPUSimRunner::default().advance_simulation(&world, &state, 60.0, world.max_stable_timestep())).unwrap();
+    ///     return world.sample_voxel_temperature(&result, 1, 1, 1).unwrap();
+    /// };
+    /// assert!((cooled_temperature(0.0) - 400.0).abs() < 0.01);
+    /// assert!(cooled_temperature(50.0) < 399.0);
+    /// assert!(cooled_temperature(50.0) > 300.0);
+    /// ```
+    pub fn with_ambient_temperature(mut self, temperature: f32) -> Self {
+        self.ambient_temperature = temperature;
+        return self;
+    }
+
+    /// Create a world from the first model of a MagicaVoxel `.vox` file, with every voxel a cell
+    /// of the given size, and each palette index of the model mapped to a material. Empty voxels
+    /// are [material::BLANK]. Fails if the file can not be read or does not contain a model
+    ///
+    /// The resolution and materials are checked as in [SimWorldBuilder::try_build], failing with
+    /// [VoxError::Build]. Models without any cells fail with [BuildError::InvalidDimensions], and
+    /// voxels outside the model's size with [VoxError::Malformed].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{BuildError, SimWorldBuilder}, material, import::VoxError};
+    /// // A 3x1x1 model with a water voxel and a copper voxel
+    /// let mut vox: Vec<u8> = b"VOX ".to_vec();
+    /// vox.extend(150u32.to_le_bytes());
+    /// let chunk = |id: &[u8], content: &[u32], children: u32| {
+    ///     let mut chunk = id.to_vec();
+    ///     chunk.extend((content.len() as u32 * 4).to_le_bytes());
+    ///     chunk.extend(children.to_le_bytes());
+    ///     chunk.extend(content.iter().flat_map(|v| v.to_le_bytes()));
+    ///     return chunk;
+    /// };
+    /// let size = chunk(b"SIZE", &[3, 1, 1], 0);
+    /// let voxels = chunk(b"XYZI", &[2, u32::from_le_bytes([0, 0, 0, 1]), u32::from_le_bytes([2, 0, 0, 2])], 0);
+    /// vox.extend(chunk(b"MAIN", &[], (size.len() + voxels.len()) as u32));
+    /// vox.extend(size);
+    /// vox.extend(voxels);
+    /// let path = std::env::temp_dir().join("thermal_rs_doctest_model.vox");
+    /// std::fs::write(&path, vox).unwrap();
+    ///
+    /// let world = SimWorldBuilder::from_vox(&path, 0.01, |index| match index {
+    ///     1 => material::WATER,
+    ///     _ => material::COPPER,
+    /// })
+    /// .unwrap();
+    /// assert_eq!((world.get_x_size(), world.get_y_size(), world.get_z_size()), (3, 1, 1));
+    /// assert_eq!(world.get_voxel_material(0, 0, 0), Some(&material::WATER));
+    /// assert_eq!(world.get_voxel_material(1, 0, 0), Some(&material::BLANK));
+    /// assert_eq!(world.get_voxel_material(2, 0, 0), Some(&material::COPPER));
+    ///
+    /// let negative = SimWorldBuilder::from_vox(&path, -0.01, |_| material::COPPER);
+    /// assert!(matches!(negative, Err(VoxError::Build(BuildError::InvalidResolution))));
+    /// ```
+    pub fn from_vox(
+        path: &std::path::Path,
+        resolution: f64,
+        palette_to_material: impl Fn(u8) -> Material,
+    ) -> Result<SimWorld, VoxError> {
+        let model = read_vox(path)?;
+        let (x_size, y_size, z_size) = model.size;
+        check_cell_size((resolution, resolution, resolution)).map_err(VoxError::Build)?;
+        if x_size == 0 || y_size == 0 || z_size == 0 {
+            return Err(VoxError::Build(BuildError::InvalidDimensions));
         }
+        let cell_count = checked_cell_count(x_size, y_size, z_size).map_err(VoxError::Build)?;
 
-        let mut material_list: Vec<Material> = Vec::new();
-        material_list.resize(material_map.len(), material::BLANK);
+        // Sizes are known in cells, so the world is made directly rather than from a size in
+        // meters which could round up to an extra cell
+        let mut world = SimWorld {
+            x_size,
+            y_size,
+            z_size,
+            cell_size: (resolution, resolution, resolution),
+            material_map: vec![material::BLANK],
+            materials: vec![0; cell_count],
+            fixed_temperatures: BTreeMap::new(),
+            boundaries: [WorldBoundary::Insulated; 3],
+            heat_sources: BTreeMap::new(),
+            scheduled_heat_sources: Vec::new(),
+            ambient_temperature: SimWorldBuilder::default().ambient_temperature,
+        };
 
-        for (k, v) in material_map.into_iter() {
-            if let Some(list_val) = material_list.get_mut(v as usize) {
-                *list_val = k;
+        let mut palette_cells: BTreeMap<u8, Vec<(usize, usize, usize)>> = BTreeMap::new();
+        for ((x, y, z), index) in model.voxels {
+            if x >= x_size || y >= y_size || z >= z_size {
+                return Err(VoxError::Malformed);
             }
+            palette_cells.entry(index).or_default().push((x, y, z));
         }
+        for (index, cells) in palette_cells {
+            let material = palette_to_material(index);
+            check_material(&material).map_err(VoxError::Build)?;
+            world.paint_brush(material, &CellList(cells));
+        }
+        return Ok(world);
+    }
 
-        return SimWorld {
+    /// Build the world with a given voxel resolution
+    ///
+    /// Every distinct material applied is given its own entry in the material map, even when
+    /// materials differ only slightly.
+    ///
+    /// Panics if the world or its materials are invalid, see [SimWorldBuilder::try_build].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume};
+    /// let conductive = Material {
+    ///     thermal_conductivity: [(0.0, 0.0, 0.005208); 3],
+    ///     ..material::WATER
+    /// };
+    /// let less_conductive = Material {
+    ///     thermal_conductivity: [(0.0, 0.0, 0.005299); 3],
+    ///     ..material::WATER
+    /// };
+    /// let world = SimWorldBuilder::new(2.0, 1.0, 1.0)
+    ///     .with_material(conductive, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .with_material(less_conductive, Box::new(AABBVolume::new(1.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+    ///     .build(1.0);
+    /// assert_eq!(world.get_material_map().len(), 3);
+    /// ```
+    pub fn build(self, resolution: f64) -> SimWorld {
+        return self
+            .try_build(resolution)
+            .expect("The world and its materials must be valid to build");
+    }
+
+    /// Build the world with a given voxel resolution, checking that the world and its materials
+    /// are valid first
+    ///
+    /// The resolution and the dimensions of the world must be positive and finite, apart from
+    /// the depth of a planar world. Every material's density and specific heat must be finite and
+    /// not negative, materials without heat capacity may have a density or specific heat of 0.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{BuildError, SimWorldBuilder}, material::{self, Material}, volume::AABBVolume};
+    /// let brush = || Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0));
+    /// assert!(SimWorldBuilder::new(1.0, 1.0, 1.0).with_material(material::WATER, brush()).try_build(0.5).is_ok());
+    ///
+    /// let resolution = SimWorldBuilder::new(1.0, 1.0, 1.0).try_build(0.0);
+    /// assert!(matches!(resolution, Err(BuildError::InvalidResolution)));
+    /// let dimensions = SimWorldBuilder::new(1.0, -1.0, 1.0).try_build(0.5);
+    /// assert!(matches!(dimensions, Err(BuildError::InvalidDimensions)));
+    ///
+    /// let dense = Material { density: -1000.0, ..material::WATER };
+    /// let density = SimWorldBuilder::new(1.0, 1.0, 1.0).with_material(dense, brush()).try_build(0.5);
+    /// assert!(matches!(density, Err(BuildError::InvalidDensity(m)) if m == dense));
+    /// let heat = Material { specific_heat: (0.0, 0.0, f32::NAN), ..material::WATER };
+    /// let specific_heat = SimWorldBuilder::new(1.0, 1.0, 1.0).with_default_material(heat).try_build(0.5);
+    /// assert!(matches!(specific_heat, Err(BuildError::InvalidSpecificHeat(_))));
+    ///
+    /// // Materials without heat capacity are allowed
+    /// let massless = Material { density: 0.0, ..material::WATER };
+    /// let heatless = Material { specific_heat: (0.0, 0.0, 0.0), ..material::WATER };
+    /// assert!(!massless.has_heat_capacity() && !heatless.has_heat_capacity());
+    /// for vacuum in [massless, heatless] {
+    ///     assert!(SimWorldBuilder::new(1.0, 1.0, 1.0).with_material(vacuum, brush()).try_build(0.5).is_ok());
+    /// }
+    ///
+    /// // Building with a cell size along each axis makes the same checks
+    /// let cell_size = SimWorldBuilder::new(1.0, 1.0, 1.0).with_material(dense, brush()).try_build_with_cell_size(0.5, 0.5, 1.0);
+    /// assert!(matches!(cell_size, Err(BuildError::InvalidDensity(_))));
+    /// ```
+    pub fn try_build(self, resolution: f64) -> Result<SimWorld, BuildError> {
+        return self.try_build_with_cell_size(resolution, resolution, resolution);
+    }
+
+    /// Build the world with cells of a given size along each axis, such as flat cells for a
+    /// thin plate
+    ///
+    /// Panics if the world or its materials are not valid, see
+    /// [SimWorldBuilder::try_build_with_cell_size].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// let conductor = Material {
+    ///     thermal_conductivity: [(0.0, 0.0, 400.0); 3],
+    ///     ..material::COPPER
+    /// };
+    /// // Two cells along x, with either a 1cm or 2cm tall face between them
+    /// let flow = |height: f64| {
+    ///     let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.02, height, 0.01);
+    ///     let world = SimWorldBuilder::new(0.02, height, 0.01)
+    ///         .with_material(conductor, Box::new(everything.clone()))
+    ///         .build_with_cell_size(0.01, height, 0.01);
+    ///     let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    ///     state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.01, height, 0.01)).unwrap();
+    ///
+    ///     let result = CPUSimRunner::default().advance_step(&world, state.clone(), 0.01);
+    ///     let (before, after) = (world.total_energy(&state), world.total_energy(&result));
+    ///     assert!(((after - before) / before).abs() < 1e-6);
+    ///     return (result.get_energies()[1] - state.get_energies()[1]) as f64;
+    /// };
+    ///
+    /// // Doubling the face area doubles the flow of heat
+    /// assert!((flow(0.02) / flow(0.01) - 2.0).abs() < 1e-3);
+    /// ```
+    pub fn build_with_cell_size(self, dx: f64, dy: f64, dz: f64) -> SimWorld {
+        return self
+            .try_build_with_cell_size(dx, dy, dz)
+            .expect("The world and its materials must be valid to build");
+    }
+
+    /// Build the world with cells of a given size along each axis, checking that the world and
+    /// its materials are valid first
+    ///
+    /// Every cell size must be positive and finite, and fine enough cells that the world's cell
+    /// count overflows are rejected as [BuildError::InvalidResolution]. The world and its
+    /// materials are checked as in [SimWorldBuilder::try_build].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{BuildError, SimWorldBuilder}, material, volume::AABBVolume};
+    /// let builder = || {
+    ///     SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///         .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    /// };
+    /// let world = builder().try_build_with_cell_size(0.5, 0.25, 1.0).unwrap();
+    /// assert_eq!((world.get_x_size(), world.get_y_size(), world.get_z_size()), (2, 4, 1));
+    ///
+    /// for (dx, dy, dz) in [(0.0, 0.1, 0.1), (0.1, -0.1, 0.1), (0.1, 0.1, f64::NAN), (1e-300, 1e-300, 1e-300)] {
+    ///     let result = builder().try_build_with_cell_size(dx, dy, dz);
+    ///     assert!(matches!(result, Err(BuildError::InvalidResolution)));
+    /// }
+    /// ```
+    pub fn try_build_with_cell_size(
+        mut self,
+        dx: f64,
+        dy: f64,
+        dz: f64,
+    ) -> Result<SimWorld, BuildError> {
+        check_cell_size((dx, dy, dz))?;
+        self.resolve_auto_size()?;
+        let size_valid = |size: f64| size.is_finite() && size > 0.0;
+        if !size_valid(self.x_size)
+            || !size_valid(self.y_size)
+            || !(self.planar || size_valid(self.z_size))
+        {
+            return Err(BuildError::InvalidDimensions);
+        }
+
+        let materials = std::iter::once(&self.default_material).chain(
+            self.brush_opperations
+                .iter()
+                .map(|(_, material, _)| material),
+        );
+        for material in materials {
+            check_material(material)?;
+        }
+
+        // Get x y and z size of world in voxels
+        let world_x = (self.x_size / dx).ceil() as usize;
+        let world_y = (self.y_size / dy).ceil() as usize;
+        let world_z = if self.planar {
+            1
+        } else {
+            (self.z_size / dz).ceil() as usize
+        };
+        let cell_count = checked_cell_count(world_x, world_y, world_z)?;
+
+        // A world can hold at most 65536 materials, so there is no use reserving more
+        let mut material_map = Vec::with_capacity(self.expected_materials.clamp(1, 65536));
+        material_map.push(self.default_material);
+
+        let mut world = SimWorld {
             x_size: world_x,
             y_size: world_y,
             z_size: world_z,
-            cell_size: resolution,
-            material_map: material_list,
-            materials: material_buffer,
+            cell_size: (dx, dy, dz),
+            material_map,
+            materials: vec![0; cell_count],
+            fixed_temperatures: BTreeMap::new(),
+            boundaries: self.boundaries,
+            heat_sources: BTreeMap::new(),
+            scheduled_heat_sources: Vec::new(),
+            ambient_temperature: self.ambient_temperature,
+        };
+
+        // Write brushes into buffer, sorting is stable so equal priorities keep insertion order
+        let mut brush_opperations = self.brush_opperations;
+        brush_opperations.sort_by_key(|(priority, _, _)| *priority);
+        for (_, mat, brush) in brush_opperations.iter() {
+            world.paint_brush(*mat, brush.as_ref());
+        }
+
+        return Ok(world);
+    }
+}
+
+/// The boundary condition at the faces of a simulation world
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WorldBoundary {
+    /// No heat flows through the face of the world
+    #[default]
+    Insulated,
+    /// The world wraps around, so cells on one face neighbor the cells on the opposite face
+    Periodic,
+}
+
+/// How a [HeatSchedule] varies between its keyframes
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    /// Each keyframe's value is held until the next keyframe
+    #[default]
+    Step,
+    /// Values are linearly interpolated between keyframes
+    Linear,
+}
+
+/// A volumetric heat source which varies over simulated time, see
+/// [SimWorld::add_scheduled_heat_source]
+///
+/// The schedule is zero before its first keyframe, and holds its last keyframe's value after it.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::world::{HeatSchedule, Interpolation};
+/// let ramp = HeatSchedule::new(vec![(1.0, 0.0), (3.0, 100.0)], Interpolation::Linear);
+/// assert_eq!(ramp.sample(0.5), 0.0);
+/// assert_eq!(ramp.sample(2.0), 50.0);
+/// assert_eq!(ramp.sample(10.0), 100.0);
+/// // 100 J / m^3 on the ramp, then 100 W / m^3 for a second
+/// assert_eq!(ramp.energy_between(0.0, 4.0), 200.0);
+///
+/// let steps = HeatSchedule::new(vec![(1.0, 0.0), (3.0, 100.0)], Interpolation::Step);
+/// assert_eq!(steps.sample(2.0), 0.0);
+/// assert_eq!(steps.sample(3.0), 100.0);
+/// assert_eq!(steps.energy_between(0.0, 4.0), 100.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeatSchedule {
+    // (simulated time in seconds, W / m^3) keyframes, sorted by time
+    keyframes: Vec<(f64, f32)>,
+    interpolation: Interpolation,
+}
+
+impl HeatSchedule {
+    /// Create a new Heat Schedule from (simulated time in seconds, W / m^3) keyframes, which
+    /// need not be in order
+    pub fn new(mut keyframes: Vec<(f64, f32)>, interpolation: Interpolation) -> HeatSchedule {
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        HeatSchedule {
+            keyframes,
+            interpolation,
+        }
+    }
+
+    /// Create a new Heat Schedule which is on at a constant W / m^3 from `start` until `end`
+    /// seconds, and off otherwise
+    pub fn pulse(watts_per_m3: f32, start: f64, end: f64) -> HeatSchedule {
+        HeatSchedule::new(vec![(start, watts_per_m3), (end, 0.0)], Interpolation::Step)
+    }
+
+    /// Get the keyframes of the schedule, sorted by time
+    pub fn get_keyframes(&self) -> &[(f64, f32)] {
+        &self.keyframes
+    }
+
+    /// Get how the schedule varies between its keyframes
+    pub fn get_interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Get the volumetric heat source in W / m^3 at a simulated time in seconds
+    pub fn sample(&self, time: f64) -> f32 {
+        let next = self.keyframes.partition_point(|k| k.0 <= time);
+        if next == 0 {
+            return 0.0;
+        }
+        let (t0, v0) = self.keyframes[next - 1];
+        return match (self.interpolation, self.keyframes.get(next)) {
+            (Interpolation::Linear, Some(&(t1, v1))) => {
+                v0 + (v1 - v0) * ((time - t0) / (t1 - t0)) as f32
+            }
+            _ => v0,
         };
     }
+
+    /// Get the energy in J / m^3 the schedule delivers between two simulated times in seconds
+    ///
+    /// This is exact for both interpolations, so the energy delivered does not depend on the
+    /// timestep used.
+    pub fn energy_between(&self, start: f64, end: f64) -> f64 {
+        let mut energy = 0.0;
+        for (index, &(t0, v0)) in self.keyframes.iter().enumerate() {
+            let next = self.keyframes.get(index + 1);
+            let (a, b) = (start.max(t0), end.min(next.map_or(f64::INFINITY, |k| k.0)));
+            if a >= b {
+                continue;
+            }
+            energy += match (self.interpolation, next) {
+                (Interpolation::Linear, Some(&(t1, v1))) => {
+                    let value = |t: f64| v0 as f64 + (v1 - v0) as f64 * (t - t0) / (t1 - t0);
+                    (value(a) + value(b)) / 2.0 * (b - a)
+                }
+                _ => v0 as f64 * (b - a),
+            };
+        }
+        return energy;
+    }
+}
+
+/// Possible errors when building a simulation world, see [SimWorldBuilder::try_build] and
+/// [SimWorldBuilder::try_build_with_cell_size]
+///
+/// Building a world from a builder or a `.vox` model, and painting materials into a world, all
+/// make the same checks. The panicking builds panic where these would be returned,
+/// [SimWorldBuilder::from_vox] returns them as [VoxError::Build], and [SimWorld::paint_material]
+/// checks the material it paints.
+#[derive(Debug)]
+pub enum BuildError {
+    /// The resolution or a cell size is not a positive, finite, size, or is so fine that the
+    /// world's cell count overflows
+    InvalidResolution,
+    /// A dimension of the world is not a positive, finite, size
+    InvalidDimensions,
+    /// A material has a negative or non finite density
+    ///
+    /// A density of exactly 0 is allowed, for materials without heat capacity, see
+    /// [Material::has_heat_capacity].
+    InvalidDensity(Material),
+    /// A material has a negative or non finite specific heat at its reference temperature
+    ///
+    /// A specific heat of exactly 0 is allowed, for materials without heat capacity, see
+    /// [Material::has_heat_capacity].
+    InvalidSpecificHeat(Material),
+    /// A world sized to fit its brushes has a brush without world space bounds, see
+    /// [SimWorldBuilder::new_auto]
+    UnboundedBrush,
+}
+
+/// Check that every cell size is a positive, finite, size
+fn check_cell_size((dx, dy, dz): (f64, f64, f64)) -> Result<(), BuildError> {
+    let size_valid = |size: f64| size.is_finite() && size > 0.0;
+    if !(size_valid(dx) && size_valid(dy) && size_valid(dz)) {
+        return Err(BuildError::InvalidResolution);
+    }
+    return Ok(());
+}
+
+/// Check that a material has a non negative, finite, density and specific heat
+fn check_material(material: &Material) -> Result<(), BuildError> {
+    if !(material.density.is_finite() && material.density >= 0.0) {
+        return Err(BuildError::InvalidDensity(*material));
+    }
+    let specific_heat = material.get_specific_heat(material.reference_temp);
+    if !(specific_heat.is_finite() && specific_heat >= 0.0) {
+        return Err(BuildError::InvalidSpecificHeat(*material));
+    }
+    return Ok(());
+}
+
+/// Count the cells of a world with the given size in cells, failing if the count overflows
+fn checked_cell_count(x_size: usize, y_size: usize, z_size: usize) -> Result<usize, BuildError> {
+    return x_size
+        .checked_mul(y_size)
+        .and_then(|count| count.checked_mul(z_size))
+        .ok_or(BuildError::InvalidResolution);
 }
 
 /// Possible errors when operating on sim states
@@ -114,9 +807,64 @@ impl SimWorldBuilder {
 pub enum SimStateOppError {
     /// Missmatch between sizes of a simulation state and a simulaton world
     StateSizeMissmatch,
+    /// The given region does not contain any cells of the simulation world
+    EmptyRegion,
+}
+
+/// Possible errors when saving or loading simulation checkpoints
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The checkpoint file could not be read or written
+    Io(std::io::Error),
+    /// The checkpoint could not be encoded
+    Encode(bincode::error::EncodeError),
+    /// The checkpoint file is not a valid checkpoint
+    Decode(bincode::error::DecodeError),
+}
+
+/// Write a value to a binary checkpoint file
+#[cfg(feature = "serde")]
+fn save_checkpoint<T: serde::Serialize>(
+    value: &T,
+    path: &std::path::Path,
+) -> Result<(), CheckpointError> {
+    let file = std::fs::File::create(path).map_err(CheckpointError::Io)?;
+    let mut writer = std::io::BufWriter::new(file);
+    bincode::serde::encode_into_std_write(value, &mut writer, bincode::config::standard())
+        .map_err(CheckpointError::Encode)?;
+    return Ok(());
+}
+
+/// Read a value from a binary checkpoint file
+#[cfg(feature = "serde")]
+fn load_checkpoint<T: serde::de::DeserializeOwned>(
+    path: &std::path::Path,
+) -> Result<T, CheckpointError> {
+    let file = std::fs::File::open(path).map_err(CheckpointError::Io)?;
+    let mut reader = std::io::BufReader::new(file);
+    return bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+        .map_err(CheckpointError::Decode);
 }
 
 /// Represents a world in which a simulation can be run
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+/// let world = SimWorldBuilder::new(1.0, 2.0, 3.0)
+///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+///     .build(0.5);
+/// let copy = world.clone();
+/// assert_eq!(
+///     (copy.get_x_size(), copy.get_y_size(), copy.get_z_size()),
+///     (world.get_x_size(), world.get_y_size(), world.get_z_size())
+/// );
+/// assert_eq!(copy.get_material_map(), world.get_material_map());
+/// assert_eq!(copy.get_materials(), world.get_materials());
+/// ```
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SimWorld {
     // The x dimension of the simulation world, in cells
     x_size: usize,
@@ -124,15 +872,65 @@ pub struct SimWorld {
     y_size: usize,
     // The z dimension of the simulation world, in cells
     z_size: usize,
-    // The side length of cells in meters
-    cell_size: f32,
+    // The (x, y, z) side lengths of cells in meters
+    cell_size: (f64, f64, f64),
     // A list of all materials present in the simulation world
     material_map: Vec<Material>,
     // A map of all materials in the world, indexing into the material_map
-    materials: Vec<u8>,
+    materials: Vec<u16>,
+    // Cells held at a fixed temperature in kelvin, by cell index
+    fixed_temperatures: BTreeMap<usize, f32>,
+    // The boundary condition along each axis
+    boundaries: [WorldBoundary; 3],
+    // Volumetric heat sources in W / m^3, by cell index
+    heat_sources: BTreeMap<usize, f32>,
+    // Volumetric heat sources which vary over simulated time, with the cell indices they cover
+    scheduled_heat_sources: Vec<(HeatSchedule, Vec<usize>)>,
+    // The temperature of the fluid surrounding the world in kelvin
+    ambient_temperature: f32,
 }
 
 impl SimWorld {
+    /// Save the world to a binary checkpoint file, requires the `serde` feature
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{SimState, SimWorld, SimWorldBuilder}, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(2.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(1.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+    ///     .build(0.5);
+    /// let state = world
+    ///     .set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0))
+    ///     .unwrap();
+    ///
+    /// let directory = std::env::temp_dir();
+    /// world.save(&directory.join("thermal_rs_doctest_world.bin")).unwrap();
+    /// state.save(&directory.join("thermal_rs_doctest_state.bin")).unwrap();
+    /// let loaded_world = SimWorld::load(&directory.join("thermal_rs_doctest_world.bin")).unwrap();
+    /// let loaded_state: SimState = SimState::load(&directory.join("thermal_rs_doctest_state.bin")).unwrap();
+    ///
+    /// assert_eq!(
+    ///     (loaded_world.get_x_size(), loaded_world.get_y_size(), loaded_world.get_z_size()),
+    ///     (world.get_x_size(), world.get_y_size(), world.get_z_size())
+    /// );
+    /// assert_eq!(loaded_world.get_cell_size(), world.get_cell_size());
+    /// assert_eq!(loaded_world.get_material_map(), world.get_material_map());
+    /// assert_eq!(loaded_world.get_materials(), world.get_materials());
+    /// assert_eq!(loaded_state.get_energies(), state.get_energies());
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &std::path::Path) -> Result<(), CheckpointError> {
+        return save_checkpoint(self, path);
+    }
+
+    /// Load a world from a binary checkpoint file written by [SimWorld::save], requires the
+    /// `serde` feature
+    #[cfg(feature = "serde")]
+    pub fn load(path: &std::path::Path) -> Result<SimWorld, CheckpointError> {
+        return load_checkpoint(path);
+    }
+
     /// Get the x dimension of the world
     pub fn get_x_size(&self) -> usize {
         self.x_size
@@ -148,60 +946,406 @@ impl SimWorld {
         self.z_size
     }
 
-    /// Gets a non-mutable buffer representing the world cell materials
-    pub fn get_materials<'a>(&'a self) -> &'a [u8] {
+    /// Get the length in meters of the world along the x axis
+    ///
+    /// Worlds are built with a whole number of cells along each axis, so this can be slightly
+    /// larger than the size the world was built with.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::SimWorldBuilder;
+    /// let world = SimWorldBuilder::new(1.0, 0.25, 0.5).build(0.1);
+    /// assert!((world.get_x_meters() - 1.0).abs() < 1e-9);
+    /// assert!((world.get_y_meters() - 0.3).abs() < 1e-9);
+    /// assert!((world.get_z_meters() - 0.5).abs() < 1e-9);
+    /// ```
+    pub fn get_x_meters(&self) -> f64 {
+        self.x_size as f64 * self.cell_size.0
+    }
+
+    /// Get the length in meters of the world along the y axis, see [SimWorld::get_x_meters]
+    pub fn get_y_meters(&self) -> f64 {
+        self.y_size as f64 * self.cell_size.1
+    }
+
+    /// Get the length in meters of the world along the z axis, see [SimWorld::get_x_meters]
+    pub fn get_z_meters(&self) -> f64 {
+        self.z_size as f64 * self.cell_size.2
+    }
+
+    /// Check whether the world is planar, a single cell deep along z, see
+    /// [SimWorldBuilder::new_2d]
+    pub fn is_planar(&self) -> bool {
+        self.z_size == 1
+    }
+
+    /// Gets a non-mutable buffer representing the world cell materials, as the index of each
+    /// cell's material in the material map. A world can hold up to 65536 distinct materials
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume};
+    /// // A column of 300 layers, each of a different material
+    /// let layer = |i: usize| Material { density: 1000.0 + i as f32, ..material::WATER };
+    /// let mut builder = SimWorldBuilder::new(1.0, 1.0, 300.0);
+    /// for i in 0..300 {
+    ///     builder = builder.with_material(layer(i), Box::new(AABBVolume::new(0.0, 0.0, i as f64, 1.0, 1.0, i as f64 + 1.0)));
+    /// }
+    /// let world = builder.build(1.0);
+    ///
+    /// assert_eq!(world.get_material_map().len(), 301);
+    /// assert_eq!(world.get_materials()[299], 300);
+    /// for i in [0, 255, 256, 299] {
+    ///     assert_eq!(world.sample_material(0.5, 0.5, i as f64 + 0.5), Some(&layer(i)));
+    /// }
+    /// ```
+    pub fn get_materials(&self) -> &[u16] {
         self.materials.as_slice()
     }
 
     /// Gets a non-mutable buffer representing the material map
-    pub fn get_material_map<'a>(&'a self) -> &'a [Material] {
+    pub fn get_material_map(&self) -> &[Material] {
         self.material_map.as_slice()
     }
 
     /// Gets the index of a cell position, returns None if out of bounds
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::SimWorldBuilder;
+    /// let world = SimWorldBuilder::new(2.0, 3.0, 4.0).build(1.0);
+    /// assert_eq!(world.get_pos_index(0, 2, 0), Some(4));
+    /// assert_eq!(world.get_pos_index(1, 2, 3), Some(23));
+    /// assert_eq!(world.get_pos_index(2, 0, 0), None);
+    /// assert_eq!(world.get_pos_index(0, 3, 0), None);
+    /// assert_eq!(world.get_pos_index(0, 0, 4), None);
+    /// ```
     pub fn get_pos_index(&self, x: usize, y: usize, z: usize) -> Option<usize> {
-        if x < self.x_size && y < self.x_size && z < self.y_size {
+        if x < self.x_size && y < self.y_size && z < self.z_size {
             Some(x + y * self.x_size + z * self.x_size * self.y_size)
         } else {
             None
         }
     }
 
-    /// Gets the index of a cell position, returns None if out of bounds
-    pub fn get_ipos_index(&self, x: i128, y: i128, z: i128) -> Option<usize> {
-        if x < 0 || y < 0 || z < 0 {
+    /// Gets the cell position of an index, returns None if out of bounds
+    pub fn get_index_pos(&self, index: usize) -> Option<(usize, usize, usize)> {
+        if index >= self.x_size * self.y_size * self.z_size {
             return None;
         }
-        if x < self.x_size as i128 && y < self.x_size as i128 && z < self.y_size as i128 {
-            Some(x as usize + y as usize * self.x_size + z as usize * self.x_size * self.y_size)
-        } else {
-            None
+        return Some((
+            index % self.x_size,
+            (index / self.x_size) % self.y_size,
+            index / (self.x_size * self.y_size),
+        ));
+    }
+
+    /// Get the boundary condition at the faces of the world along an axis
+    pub fn get_boundary(&self, axis: Axis) -> WorldBoundary {
+        self.boundaries[axis as usize]
+    }
+
+    /// Get the temperature in kelvin of the fluid surrounding the world
+    pub fn get_ambient_temperature(&self) -> f32 {
+        self.ambient_temperature
+    }
+
+    /// Gets the index of a signed cell position, returns None if out of bounds
+    ///
+    /// Positions outside the world along a [WorldBoundary::Periodic] axis wrap around to the
+    /// opposite face.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::SimWorldBuilder;
+    /// let world = SimWorldBuilder::new(2.0, 3.0, 4.0).build(1.0);
+    /// let offsets = [(1, 0, 0), (0, 1, 0), (0, 0, 1), (-1, 0, 0), (0, -1, 0), (0, 0, -1)];
+    /// for x in [0, 1] {
+    ///     for y in [0, 2] {
+    ///         for z in [0, 3] {
+    ///             let neighbors = offsets
+    ///                 .iter()
+    ///                 .filter_map(|(dx, dy, dz)| world.get_ipos_index(x + dx, y + dy, z + dz))
+    ///                 .count();
+    ///             assert_eq!(neighbors, 3);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn get_ipos_index(&self, x: i128, y: i128, z: i128) -> Option<usize> {
+        let x = self.wrap_coordinate(x, self.x_size, Axis::X)?;
+        let y = self.wrap_coordinate(y, self.y_size, Axis::Y)?;
+        let z = self.wrap_coordinate(z, self.z_size, Axis::Z)?;
+        return self.get_pos_index(x, y, z);
+    }
+
+    /// Bring a signed coordinate along an axis into the world, wrapping it if the axis is
+    /// periodic. Returns None if the coordinate is out of bounds
+    fn wrap_coordinate(&self, coordinate: i128, size: usize, axis: Axis) -> Option<usize> {
+        if size == 0 {
+            return None;
         }
+        return match self.get_boundary(axis) {
+            WorldBoundary::Insulated => usize::try_from(coordinate).ok().filter(|c| *c < size),
+            WorldBoundary::Periodic => Some(coordinate.rem_euclid(size as i128) as usize),
+        };
     }
 
-    /// Get the 3d volume of a single cell
-    pub fn get_cell_volume(&self) -> f32 {
-        self.cell_size.powf(3.0) as f32
+    /// Get the 3d volume of a single cell in m^3
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::Axis};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0).build(0.1);
+    /// assert!((world.get_cell_volume() - 0.001).abs() < 1e-12);
+    /// assert_eq!(world.get_cell_length(Axis::Y), 0.1);
+    ///
+    /// let plate = SimWorldBuilder::new(1.0, 1.0, 0.01).build_with_cell_size(0.1, 0.1, 0.01);
+    /// assert!((plate.get_cell_volume() - 0.0001).abs() < 1e-12);
+    /// assert_eq!(plate.get_cell_size(), (0.1, 0.1, 0.01));
+    /// ```
+    pub fn get_cell_volume(&self) -> f64 {
+        self.cell_size.0 * self.cell_size.1 * self.cell_size.2
     }
 
-    /// Get the side length of a cell
-    pub fn get_cell_length(&self) -> f32 {
+    /// Get the (x, y, z) side lengths of a cell in m
+    pub fn get_cell_size(&self) -> (f64, f64, f64) {
         self.cell_size
     }
 
+    /// Get the side length of a cell along an axis in m
+    pub fn get_cell_length(&self, axis: Axis) -> f64 {
+        match axis {
+            Axis::X => self.cell_size.0,
+            Axis::Y => self.cell_size.1,
+            Axis::Z => self.cell_size.2,
+        }
+    }
+
+    /// Get the largest timestep in seconds for which explicit simulation of this world is stable
+    ///
+    /// This is the explicit diffusion limit `1 / (2 * a * (1/dx^2 + 1/dy^2 + 1/dz^2))`, which is
+    /// `dx^2 / (6 * a)` for cubic cells, where `dx`, `dy` and `dz` are the cell sizes and `a` is
+    /// the largest thermal diffusivity of any material in the world, evaluated at that material's
+    /// reference temperature. Returns infinity if no material in the world conducts heat.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// // Water diffuses slowly, so the limit is a few hours at this resolution
+    /// let limit = world.max_stable_timestep();
+    /// assert!(limit > 10_000.0 && limit < 12_000.0);
+    ///
+    /// let empty = SimWorldBuilder::new(1.0, 1.0, 1.0).build(0.1);
+    /// assert_eq!(empty.max_stable_timestep(), f64::INFINITY);
+    /// ```
+    pub fn max_stable_timestep(&self) -> f64 {
+        let max_diffusivity = self
+            .material_map
+            .iter()
+            .filter(|material| material.has_heat_capacity())
+            .map(|material| material.get_thermal_diffusivity(material.reference_temp) as f64)
+            .fold(0.0, f64::max);
+        if max_diffusivity <= 0.0 {
+            return f64::INFINITY;
+        }
+        let (dx, dy, dz) = self.cell_size;
+        let inverse_square_sum = dx.powi(-2) + dy.powi(-2) + dz.powi(-2);
+        return 1.0 / (2.0 * max_diffusivity * inverse_square_sum);
+    }
+
     /// Check if a simulation state can be a valid state of this world
-    pub fn is_state_valid(&self, state: &SimState) -> bool {
-        self.materials.len() == state.energies.len()
+    ///
+    /// Runners rely on this check before indexing into a state by world positions.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::{SimState, SimWorldBuilder};
+    /// let world = SimWorldBuilder::new(2.0, 2.0, 2.0).build(1.0);
+    /// assert!(world.is_state_valid(&world.get_blank_sim_state()));
+    ///
+    /// let wrong_size: SimState = vec![0.0; 5].into_iter().collect();
+    /// assert!(!world.is_state_valid(&wrong_size));
+    /// ```
+    pub fn is_state_valid<E: Float>(&self, state: &SimState<E>) -> bool {
+        state.energies.len() == self.x_size * self.y_size * self.z_size
     }
 
     /// Samples the material stats at the voxel closest to the given point, returns None if given
     /// point is out of bounds
-    pub fn sample_material(&self, x: f32, y: f32, z: f32) -> Option<&Material> {
-        self.get_voxel_material(
-            (x / self.cell_size).floor() as usize,
-            (y / self.cell_size).floor() as usize,
-            (z / self.cell_size).floor() as usize,
+    pub fn sample_material(&self, x: f64, y: f64, z: f64) -> Option<&Material> {
+        let (x, y, z) = self.get_point_voxel(x, y, z)?;
+        self.get_voxel_material(x, y, z)
+    }
+
+    /// Get the voxel containing a given world space point, returns None if the point has a
+    /// negative or NaN coordinate
+    fn get_point_voxel(&self, x: f64, y: f64, z: f64) -> Option<(usize, usize, usize)> {
+        if !(x >= 0.0 && y >= 0.0 && z >= 0.0) {
+            return None;
+        }
+        return Some((
+            (x / self.cell_size.0).floor() as usize,
+            (y / self.cell_size.1).floor() as usize,
+            (z / self.cell_size.2).floor() as usize,
+        ));
+    }
+
+    /// Get the coordinates of every voxel filled with a given material. Returns an empty list if
+    /// the material is not present in the world.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(3.0, 2.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(1.0, 0.0, 0.0, 2.0, 2.0, 1.0)))
+    ///     .build(1.0);
+    /// assert_eq!(world.cells_with_material(&material::WATER), vec![(1, 0, 0), (1, 1, 0)]);
+    /// assert!(world.cells_with_material(&material::COPPER).is_empty());
+    /// ```
+    pub fn cells_with_material(&self, material: &Material) -> Vec<(usize, usize, usize)> {
+        let material_index = match self.material_map.iter().position(|m| m == material) {
+            Some(i) => i as u16,
+            None => {
+                return Vec::new();
+            }
+        };
+        return self
+            .materials
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| **m == material_index)
+            .filter_map(|(i, _)| self.get_index_pos(i))
+            .collect();
+    }
+
+    /// Count the cells filled with each material, by index in the material map. Every material in
+    /// the map is included, so materials whose brushes were entirely covered by other brushes or
+    /// fell outside the world are counted as 0
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::{AABBVolume, CellIterator}};
+    /// // A brush hanging off the edge of the world, and one entirely outside it
+    /// let clipped = AABBVolume::new(1.0, 0.0, 0.0, 3.0, 2.0, 2.0);
+    /// let world = SimWorldBuilder::new(2.0, 2.0, 2.0)
+    ///     .with_material(material::WATER, Box::new(clipped.clone()))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(5.0, 5.0, 5.0, 6.0, 6.0, 6.0)))
+    ///     .build(1.0);
+    /// let counts = world.material_cell_counts();
+    /// assert_eq!(clipped.cell_count(world.get_cell_size()), 8);
+    /// assert_eq!(counts[&1], 4);
+    /// assert_eq!(counts[&2], 0);
+    /// assert_eq!(counts.values().sum::<usize>(), 8);
+    /// ```
+    pub fn material_cell_counts(&self) -> HashMap<usize, usize> {
+        let mut counts: HashMap<usize, usize> =
+            (0..self.material_map.len()).map(|i| (i, 0)).collect();
+        for material in self.materials.iter() {
+            *counts.entry(*material as usize).or_insert(0) += 1;
+        }
+        return counts;
+    }
+
+    /// Get the exposed surface area in m^2 of the cells filled with a material, the total area of
+    /// their faces which border a different material or the edge of the world
+    ///
+    /// Faces across a periodic boundary border the cell on the opposite side of the world, so are
+    /// only counted if it holds a different material. Returns 0 if the material is not present.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{SimWorldBuilder, WorldBoundary}, material::{self, Axis}, volume::AABBVolume};
+    /// // A 0.3m cube of copper in water
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.2, 0.2, 0.2, 0.5, 0.5, 0.5)))
+    ///     .build(0.1);
+    /// let area = world.material_surface_area(&material::COPPER);
+    /// assert!((area - 6.0 * 0.3 * 0.3).abs() < 1e-9);
+    ///
+    /// // The water's surface includes the faces of the world and the faces of the cube
+    /// assert!((world.material_surface_area(&material::WATER) - 6.0 - area).abs() < 1e-9);
+    ///
+    /// // A slab spanning a periodic axis has no surface along it
+    /// let slab = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 0.5)))
+    ///     .with_boundary(Axis::X, WorldBoundary::Periodic)
+    ///     .with_boundary(Axis::Y, WorldBoundary::Periodic)
+    ///     .build(0.1);
+    /// assert!((slab.material_surface_area(&material::COPPER) - 2.0).abs() < 1e-9);
+    /// ```
+    pub fn material_surface_area(&self, material: &Material) -> f64 {
+        let material_index = match self.material_map.iter().position(|m| m == material) {
+            Some(i) => i as u16,
+            None => {
+                return 0.0;
+            }
+        };
+
+        let face_areas = [Axis::X, Axis::Y, Axis::Z]
+            .map(|axis| self.get_cell_volume() / self.get_cell_length(axis));
+        let mut area = 0.0;
+        for (index, _) in self
+            .materials
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| **m == material_index)
+        {
+            let (x, y, z) = self.get_index_pos(index).expect("Indicies are in bounds");
+            let (x, y, z) = (x as i128, y as i128, z as i128);
+            let neighbors = [
+                ((x - 1, y, z), Axis::X),
+                ((x + 1, y, z), Axis::X),
+                ((x, y - 1, z), Axis::Y),
+                ((x, y + 1, z), Axis::Y),
+                ((x, y, z - 1), Axis::Z),
+                ((x, y, z + 1), Axis::Z),
+            ];
+            for ((nx, ny, nz), axis) in neighbors {
+                let exposed = match self.get_ipos_index(nx, ny, nz) {
+                    Some(neighbor) => self.materials[neighbor] != material_index,
+                    None => true,
+                };
+                if exposed {
+                    area += face_areas[axis as usize];
+                }
+            }
+        }
+        return area;
+    }
+
+    /// Get the material index of every cell as a 3d array, indexed as `[[x, y, z]]`, requires the
+    /// `ndarray` feature
+    ///
+    /// The array has a shape of `(x_size, y_size, z_size)`, with x as the fastest changing axis in
+    /// memory to match [SimWorld::get_materials].
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(4.0, 3.0, 2.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(1.0, 0.0, 0.0, 2.0, 3.0, 2.0)))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 2.0, 1.0, 4.0, 3.0, 2.0)))
+    ///     .build(1.0);
+    /// let grid = world.material_grid();
+    /// assert_eq!(grid.shape(), &[4, 3, 2]);
+    /// for (x, y, z) in [(0, 0, 0), (1, 1, 0), (1, 2, 1), (3, 2, 1), (3, 0, 1)] {
+    ///     let material = &world.get_material_map()[grid[[x, y, z]] as usize];
+    ///     assert_eq!(Some(material), world.get_voxel_material(x, y, z));
+    /// }
+    /// ```
+    #[cfg(feature = "ndarray")]
+    pub fn material_grid(&self) -> ndarray::Array3<u16> {
+        use ndarray::ShapeBuilder;
+        return ndarray::Array3::from_shape_vec(
+            (self.x_size, self.y_size, self.z_size).f(),
+            self.materials.clone(),
         )
+        .expect("The material buffer holds one value for every cell");
     }
 
     /// Get the material value at a given voxel. Returns none if voxel is out of bounds.
@@ -228,21 +1372,50 @@ impl SimWorld {
         SimState { energies }
     }
 
+    /// Gets a simulation state with every cell at the world's ambient temperature, see
+    /// [SimWorldBuilder::with_ambient_temperature]
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_default_material(material::AIR)
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.5, 0.5, 0.5)))
+    ///     .with_ambient_temperature(310.0)
+    ///     .build(0.25);
+    /// let state = world.get_ambient_sim_state();
+    /// let field = world.temperature_field(&state).unwrap();
+    /// assert_eq!(field.len(), 64);
+    /// assert!(field.iter().all(|t| (t - 310.0).abs() < 0.01));
+    /// ```
+    pub fn get_ambient_sim_state(&self) -> SimState {
+        let cell_volume = self.get_cell_volume() as f32;
+        let energies: Vec<f32> = self
+            .materials
+            .iter()
+            .map(|mat_id| {
+                self.material_map[*mat_id as usize]
+                    .energy_in_volume(self.ambient_temperature, cell_volume)
+            })
+            .collect();
+        SimState { energies }
+    }
+
     /// Sets the temperature of a simulation state within a brush. Fails if state has a differnet
     ///  bounds size
-    pub fn set_sim_state_temperature(
+    pub fn set_sim_state_temperature<E: Float>(
         &self,
-        mut sim_state: SimState,
+        mut sim_state: SimState<E>,
         temperature: f32,
         brush: &impl CellIterator,
-    ) -> Result<SimState, SimStateOppError> {
+    ) -> Result<SimState<E>, SimStateOppError> {
         if !self.is_state_valid(&sim_state) {
             return Err(SimStateOppError::StateSizeMissmatch);
         }
 
-        let cell_volume = self.cell_size.powf(3.0) as f32;
+        let cell_volume = self.get_cell_volume() as f32;
         for index in brush
-            .cell_iter(self.cell_size)
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
             .filter_map(|x| self.get_pos_index(x.0, x.1, x.2))
         {
             let cell_mat_id = self
@@ -253,75 +1426,1612 @@ impl SimWorld {
                 .material_map
                 .get(*cell_mat_id as usize)
                 .expect("Cell material IDs are static and must be valid");
-            let cell_mass: f32 = cell_volume * cell_material.density;
             if let Some(e) = sim_state.energies.get_mut(index) {
-                *e = (temperature * cell_mass * cell_material.specific_heat) as f32;
+                *e = E::from_f32(cell_material.energy_in_volume(temperature, cell_volume));
             }
         }
         return Ok(sim_state);
     }
 
+    /// Sets the temperature of a simulation state within a brush, linearly interpolating from
+    /// `start_temperature` at the brush's lowest cells along an axis to `end_temperature` at its
+    /// highest. Only cells of the brush within the world are counted towards its extent. Fails if
+    /// state has a differnet bounds size
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Axis}, volume::AABBVolume};
+    /// // A stratified column of water, with copper at its midpoint
+    /// let column = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 5.0);
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 5.0)
+    ///     .with_material(material::WATER, Box::new(column.clone()))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 2.0, 1.0, 1.0, 3.0)))
+    ///     .build(1.0);
+    /// let state = world
+    ///     .set_sim_state_temperature_gradient(world.get_blank_sim_state(), &column, 300.0, 340.0, Axis::Z)
+    ///     .unwrap();
+    ///
+    /// for (z, expected) in [(0, 300.0), (2, 320.0), (4, 340.0)] {
+    ///     assert!((world.sample_voxel_temperature(&state, 0, 0, z).unwrap() - expected).abs() < 0.01);
+    /// }
+    /// ```
+    pub fn set_sim_state_temperature_gradient<E: Float>(
+        &self,
+        mut sim_state: SimState<E>,
+        brush: &impl CellIterator,
+        start_temperature: f32,
+        end_temperature: f32,
+        axis: Axis,
+    ) -> Result<SimState<E>, SimStateOppError> {
+        if !self.is_state_valid(&sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+
+        let cells: Vec<(usize, usize)> = brush
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
+            .filter_map(|pos| {
+                let index = self.get_pos_index(pos.0, pos.1, pos.2)?;
+                let along = match axis {
+                    Axis::X => pos.0,
+                    Axis::Y => pos.1,
+                    Axis::Z => pos.2,
+                };
+                Some((index, along))
+            })
+            .collect();
+        let (Some(start), Some(end)) = (
+            cells.iter().map(|(_, along)| *along).min(),
+            cells.iter().map(|(_, along)| *along).max(),
+        ) else {
+            return Ok(sim_state);
+        };
+
+        let cell_volume = self.get_cell_volume() as f32;
+        for (index, along) in cells {
+            let fraction = if end > start {
+                (along - start) as f32 / (end - start) as f32
+            } else {
+                0.0
+            };
+            let temperature = start_temperature + (end_temperature - start_temperature) * fraction;
+            let cell_material = self.material_map[self.materials[index] as usize];
+            sim_state.energies[index] =
+                E::from_f32(cell_material.energy_in_volume(temperature, cell_volume));
+        }
+        return Ok(sim_state);
+    }
+
+    /// Sets the temperature of every cell of a given material in a simulation state. Fails if
+    /// state has a differnet bounds size
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(3.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 3.0, 1.0, 1.0)))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(1.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+    ///     .build(1.0);
+    /// let mut state = world
+    ///     .set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 3.0, 1.0, 1.0))
+    ///     .unwrap();
+    /// state = world.set_temperature_for_material(state, &material::COPPER, 400.0).unwrap();
+    ///
+    /// for (x, expected) in [(0, 300.0), (1, 400.0), (2, 300.0)] {
+    ///     assert!((world.sample_voxel_temperature(&state, x, 0, 0).unwrap() - expected).abs() < 0.01);
+    /// }
+    /// ```
+    pub fn set_temperature_for_material<E: Float>(
+        &self,
+        mut sim_state: SimState<E>,
+        material: &Material,
+        temperature: f32,
+    ) -> Result<SimState<E>, SimStateOppError> {
+        if !self.is_state_valid(&sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+
+        let energy =
+            E::from_f32(material.energy_in_volume(temperature, self.get_cell_volume() as f32));
+        for (x, y, z) in self.cells_with_material(material) {
+            let index = self
+                .get_pos_index(x, y, z)
+                .expect("Positions of material cells are within the world");
+            sim_state.energies[index] = energy;
+        }
+        return Ok(sim_state);
+    }
+
+    /// Adds energy in joules to a simulation state around a point, spread over the cells whose
+    /// center is within `radius` of it. Each cell's share is weighted by `1 / (1 + d)`, where `d`
+    /// is its distance from the point in cells, so that the energy falls off smoothly away from
+    /// the point. If no cell center is within the radius the energy is all added to the cell
+    /// containing the point
+    ///
+    /// Fails if state has a differnet bounds size, or if there are no cells to add energy to.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// let before = world.get_blank_sim_state();
+    /// let after = world.set_point_source(before.clone(), (0.55, 0.55, 0.55), 1000.0, 0.3).unwrap();
+    ///
+    /// let deposited = world.total_energy(&after) - world.total_energy(&before);
+    /// assert!((deposited - 1000.0).abs() < 1e-3);
+    ///
+    /// // Most energy is added at the point, falling off towards the edge of the radius
+    /// let center = world.sample_voxel_temperature(&after, 5, 5, 5).unwrap();
+    /// let edge = world.sample_voxel_temperature(&after, 7, 5, 5).unwrap();
+    /// let outside = world.sample_voxel_temperature(&after, 9, 5, 5).unwrap();
+    /// assert!(center > edge && edge > outside);
+    /// ```
+    pub fn set_point_source<E: Float>(
+        &self,
+        mut sim_state: SimState<E>,
+        center: (f64, f64, f64),
+        total_energy: f64,
+        radius: f64,
+    ) -> Result<SimState<E>, SimStateOppError> {
+        if !self.is_state_valid(&sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+
+        let (cx, cy, cz) = center;
+        let mut weights: Vec<(usize, f64)> = SphereVolume::new(cx, cy, cz, radius)
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
+            .filter_map(|(x, y, z)| {
+                let index = self.get_pos_index(x, y, z)?;
+                let distance = ((((x as f64 + 0.5) * self.cell_size.0 - cx) / self.cell_size.0)
+                    .powi(2)
+                    + (((y as f64 + 0.5) * self.cell_size.1 - cy) / self.cell_size.1).powi(2)
+                    + (((z as f64 + 0.5) * self.cell_size.2 - cz) / self.cell_size.2).powi(2))
+                .sqrt();
+                Some((index, 1.0 / (1.0 + distance)))
+            })
+            .collect();
+        if weights.is_empty() {
+            let index = self
+                .get_point_voxel(cx, cy, cz)
+                .and_then(|(x, y, z)| self.get_pos_index(x, y, z))
+                .ok_or(SimStateOppError::EmptyRegion)?;
+            weights.push((index, 1.0));
+        }
+
+        let total_weight: f64 = weights.iter().map(|(_, w)| w).sum();
+        for (index, weight) in weights {
+            let energy = sim_state.energies[index].to_f64() + total_energy * weight / total_weight;
+            sim_state.energies[index] = E::from_f64(energy);
+        }
+        return Ok(sim_state);
+    }
+
+    /// Fills the volume defined by a brush with a material, adding it to the material map if it
+    /// is not already present. Simulation states for this world remain valid, but the energy of
+    /// repainted cells is unchanged, so their temperatures will change.
+    ///
+    /// The material is checked as in [SimWorldBuilder::try_build], an invalid material is not
+    /// painted.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{BuildError, SimWorldBuilder}, material::{self, Material}, volume::AABBVolume};
+    /// let mut world = SimWorldBuilder::new(2.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+    ///     .build(1.0);
+    /// let state = world.get_blank_sim_state();
+    /// world.paint_material(material::COPPER, &AABBVolume::new(1.0, 0.0, 0.0, 2.0, 1.0, 1.0)).unwrap();
+    ///
+    /// assert_eq!(world.sample_material(0.5, 0.5, 0.5), Some(&material::WATER));
+    /// assert_eq!(world.sample_material(1.5, 0.5, 0.5), Some(&material::COPPER));
+    /// assert!(world.is_state_valid(&state));
+    ///
+    /// let dense = Material { density: -1000.0, ..material::WATER };
+    /// let result = world.paint_material(dense, &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0));
+    /// assert!(matches!(result, Err(BuildError::InvalidDensity(_))));
+    /// assert_eq!(world.sample_material(0.5, 0.5, 0.5), Some(&material::WATER));
+    /// ```
+    pub fn paint_material(
+        &mut self,
+        material: Material,
+        brush: &impl CellIterator,
+    ) -> Result<(), BuildError> {
+        check_material(&material)?;
+        self.paint_brush(material, brush);
+        return Ok(());
+    }
+
+    /// Extract the cells of a world and simulation state within a region as a new, smaller, world
+    /// and state. Parts of the region outside the world are ignored.
+    ///
+    /// The material map of the new world keeps the default material at index 0, followed by only
+    /// the materials present in the region, and fixed temperatures and heat sources within the
+    /// region are kept. Periodic boundaries are
+    /// only kept along axes where the region spans the whole world.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// let mut state = world.get_blank_sim_state();
+    /// for x in 0..10 {
+    ///     let slice = AABBVolume::new(x as f64 * 0.1, 0.0, 0.0, (x + 1) as f64 * 0.1, 1.0, 1.0);
+    ///     state = world.set_sim_state_temperature(state, 300.0 + x as f32, &slice).unwrap();
+    /// }
+    ///
+    /// let center = AABBVolume::new(0.35, 0.35, 0.35, 0.65, 0.65, 0.65);
+    /// let (cropped_world, cropped_state) = world.crop_with_state(&state, &center).unwrap();
+    /// assert_eq!(cropped_world.get_x_size(), 4);
+    /// assert_eq!(cropped_world.get_material_map(), &[material::BLANK, material::WATER]);
+    /// for x in 0..4 {
+    ///     let original = world.sample_voxel_temperature(&state, x + 3, 4, 5).unwrap();
+    ///     let cropped = cropped_world.sample_voxel_temperature(&cropped_state, x, 1, 2).unwrap();
+    ///     assert_eq!(original, cropped);
+    /// }
+    /// ```
+    pub fn crop_with_state<E: Float>(
+        &self,
+        sim_state: &SimState<E>,
+        region: &AABBVolume,
+    ) -> Result<(SimWorld, SimState<E>), SimStateOppError> {
+        if !self.is_state_valid(sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+
+        // Find the range of cells within both the region and the world
+        let (min, max) = region
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
+            .filter(|&(x, y, z)| self.get_pos_index(x, y, z).is_some())
+            .fold(
+                ((usize::MAX, usize::MAX, usize::MAX), (0, 0, 0)),
+                |(min, max), (x, y, z)| {
+                    (
+                        (min.0.min(x), min.1.min(y), min.2.min(z)),
+                        (max.0.max(x), max.1.max(y), max.2.max(z)),
+                    )
+                },
+            );
+        if min.0 > max.0 {
+            return Err(SimStateOppError::EmptyRegion);
+        }
+        let (x_size, y_size, z_size) = (max.0 - min.0 + 1, max.1 - min.1 + 1, max.2 - min.2 + 1);
+
+        let mut boundaries = [WorldBoundary::Insulated; 3];
+        for (axis, (size, world_size)) in [
+            (x_size, self.x_size),
+            (y_size, self.y_size),
+            (z_size, self.z_size),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if size == world_size {
+                boundaries[axis] = self.boundaries[axis];
+            }
+        }
+
+        let mut world = SimWorld {
+            x_size,
+            y_size,
+            z_size,
+            cell_size: self.cell_size,
+            material_map: vec![self.material_map[0]],
+            materials: Vec::with_capacity(x_size * y_size * z_size),
+            fixed_temperatures: BTreeMap::new(),
+            boundaries,
+            heat_sources: BTreeMap::new(),
+            scheduled_heat_sources: Vec::new(),
+            ambient_temperature: self.ambient_temperature,
+        };
+        let mut energies: Vec<E> = Vec::with_capacity(x_size * y_size * z_size);
+        let mut remapped: Vec<Option<u16>> = vec![None; self.material_map.len()];
+        remapped[0] = Some(0);
+
+        for z in min.2..=max.2 {
+            for y in min.1..=max.1 {
+                for x in min.0..=max.0 {
+                    let index = self
+                        .get_pos_index(x, y, z)
+                        .expect("Cropped cells are within the world");
+                    let new_index = world.materials.len();
+                    let material_id = self.materials[index] as usize;
+                    let new_material_id = match remapped[material_id] {
+                        Some(i) => i,
+                        None => {
+                            world.material_map.push(self.material_map[material_id]);
+                            let i = (world.material_map.len() - 1) as u16;
+                            remapped[material_id] = Some(i);
+                            i
+                        }
+                    };
+
+                    world.materials.push(new_material_id);
+                    energies.push(sim_state.energies[index]);
+                    if let Some(t) = self.fixed_temperatures.get(&index) {
+                        world.fixed_temperatures.insert(new_index, *t);
+                    }
+                    if let Some(s) = self.heat_sources.get(&index) {
+                        world.heat_sources.insert(new_index, *s);
+                    }
+                }
+            }
+        }
+        for (schedule, cells) in self.scheduled_heat_sources.iter() {
+            let cropped_cells: Vec<usize> = cells
+                .iter()
+                .filter_map(|&index| self.get_index_pos(index))
+                .filter(|&(x, y, z)| {
+                    (min.0..=max.0).contains(&x)
+                        && (min.1..=max.1).contains(&y)
+                        && (min.2..=max.2).contains(&z)
+                })
+                .filter_map(|(x, y, z)| world.get_pos_index(x - min.0, y - min.1, z - min.2))
+                .collect();
+            if !cropped_cells.is_empty() {
+                world
+                    .scheduled_heat_sources
+                    .push((schedule.clone(), cropped_cells));
+            }
+        }
+
+        return Ok((world, energies.into_iter().collect()));
+    }
+
+    /// Create a coarser copy of the world for quick previews, merging each cube of `factor`
+    /// cells along every axis into a single cell, along with a function converting states of this
+    /// world to states of the coarse world
+    ///
+    /// Merged cells take the volume-weighted mix of their materials, see [Material::mix]. This is
+    /// lossy, as mixing blurs sharp interfaces and approximates phase changes, so coarse results
+    /// are only a guide to the full resolution ones. Converted states sum the energies of merged
+    /// cells, conserving the total energy. Where the world's size is not a multiple of `factor`
+    /// the cells on its far faces are padded with the default material at the ambient
+    /// temperature.
+    ///
+    /// Fixed temperatures hold their mean over each merged cell, and heat sources are averaged.
+    /// Panics if `factor` is zero, or if the mixes need more than 65536 distinct materials. The
+    /// conversion function panics if the state is not a valid state of this world.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(everything.clone()))
+    ///     .build(0.1);
+    /// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    ///
+    /// let (coarse_world, to_coarse) = world.downsample(2);
+    /// assert_eq!(
+    ///     (coarse_world.get_x_size(), coarse_world.get_y_size(), coarse_world.get_z_size()),
+    ///     (5, 5, 5)
+    /// );
+    /// assert_eq!(coarse_world.get_cell_size(), (0.2, 0.2, 0.2));
+    /// assert_eq!(coarse_world.get_material_map(), &[material::BLANK, material::WATER]);
+    ///
+    /// // A uniform world keeps its temperature
+    /// let coarse_state = to_coarse(&state);
+    /// let (min, max) = coarse_world.temperature_extremes(&coarse_state).unwrap();
+    /// assert!((min - 300.0).abs() < 0.01 && (max - 300.0).abs() < 0.01);
+    /// assert!((coarse_world.total_energy(&coarse_state) - world.total_energy(&state)).abs() < 1.0);
+    /// ```
+    pub fn downsample<E: Float>(
+        &self,
+        factor: usize,
+    ) -> (SimWorld, impl Fn(&SimState<E>) -> SimState<E> + use<E>) {
+        assert!(
+            factor > 0,
+            "Worlds can only be downsampled by a positive factor"
+        );
+        let (x_size, y_size, z_size) = (
+            self.x_size.div_ceil(factor),
+            self.y_size.div_ceil(factor),
+            self.z_size.div_ceil(factor),
+        );
+        let cells_per_coarse = factor * factor * factor;
+
+        // The coarse cell each cell is merged into, in the same order as the cell materials
+        let mut coarse_indices: Vec<usize> = Vec::with_capacity(self.materials.len());
+        for z in 0..self.z_size {
+            for y in 0..self.y_size {
+                for x in 0..self.x_size {
+                    coarse_indices
+                        .push(x / factor + (y / factor) * x_size + (z / factor) * x_size * y_size);
+                }
+            }
+        }
+
+        // Count the cells of each material in every coarse cell, padding with the default material
+        let mut material_counts: Vec<Vec<(u16, usize)>> =
+            vec![Vec::new(); x_size * y_size * z_size];
+        for (material_id, coarse_index) in self.materials.iter().zip(coarse_indices.iter()) {
+            let counts = &mut material_counts[*coarse_index];
+            match counts.iter_mut().find(|(id, _)| id == material_id) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*material_id, 1)),
+            }
+        }
+        let padding: Vec<usize> = material_counts
+            .iter()
+            .map(|counts| cells_per_coarse - counts.iter().map(|(_, n)| n).sum::<usize>())
+            .collect();
+
+        let mut world = SimWorld {
+            x_size,
+            y_size,
+            z_size,
+            cell_size: (
+                self.cell_size.0 * factor as f64,
+                self.cell_size.1 * factor as f64,
+                self.cell_size.2 * factor as f64,
+            ),
+            material_map: vec![self.material_map[0]],
+            materials: Vec::with_capacity(x_size * y_size * z_size),
+            fixed_temperatures: BTreeMap::new(),
+            boundaries: self.boundaries,
+            heat_sources: BTreeMap::new(),
+            scheduled_heat_sources: Vec::new(),
+            ambient_temperature: self.ambient_temperature,
+        };
+
+        for (mut counts, padded) in material_counts.into_iter().zip(padding.iter()) {
+            if *padded > 0 {
+                counts.push((0, *padded));
+            }
+            let mut merged = 0;
+            let mut mixed = self.material_map[0];
+            for (material_id, count) in counts {
+                let material = self.material_map[material_id as usize];
+                mixed = match merged {
+                    0 => material,
+                    _ => Material::mix(&mixed, &material, merged as f32 / (merged + count) as f32),
+                };
+                merged += count;
+            }
+
+            let index = match world.material_map.iter().position(|m| *m == mixed) {
+                Some(i) => i as u16,
+                None => {
+                    let new_index = world.material_map.len();
+                    assert!(
+                        new_index <= u16::MAX as usize,
+                        "There can be at most 65536 distinct materials present in a simulation."
+                    );
+                    world.material_map.push(mixed);
+                    new_index as u16
+                }
+            };
+            world.materials.push(index);
+        }
+
+        let mut fixed: BTreeMap<usize, (f32, usize)> = BTreeMap::new();
+        for (index, temperature) in self.fixed_temperatures.iter() {
+            let (sum, count) = fixed.entry(coarse_indices[*index]).or_insert((0.0, 0));
+            *sum += temperature;
+            *count += 1;
+        }
+        for (coarse_index, (sum, count)) in fixed {
+            world
+                .fixed_temperatures
+                .insert(coarse_index, sum / count as f32);
+        }
+        for (index, watts_per_m3) in self.heat_sources.iter() {
+            *world
+                .heat_sources
+                .entry(coarse_indices[*index])
+                .or_insert(0.0) += watts_per_m3 / cells_per_coarse as f32;
+        }
+
+        // Scale each schedule by the fraction of every coarse cell it covers
+        for (schedule, cells) in self.scheduled_heat_sources.iter() {
+            let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+            for index in cells.iter() {
+                *counts.entry(coarse_indices[*index]).or_insert(0) += 1;
+            }
+            let mut by_count: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for (coarse_index, count) in counts {
+                by_count.entry(count).or_default().push(coarse_index);
+            }
+            for (count, coarse_cells) in by_count {
+                let fraction = count as f32 / cells_per_coarse as f32;
+                let scaled = HeatSchedule {
+                    keyframes: schedule
+                        .keyframes
+                        .iter()
+                        .map(|&(time, watts_per_m3)| (time, watts_per_m3 * fraction))
+                        .collect(),
+                    interpolation: schedule.interpolation,
+                };
+                world.scheduled_heat_sources.push((scaled, coarse_cells));
+            }
+        }
+
+        let cell_volume = self.get_cell_volume() as f32;
+        let padding_energy =
+            self.material_map[0].energy_in_volume(self.ambient_temperature, cell_volume);
+        let initial_energies: Vec<E> = padding
+            .iter()
+            .map(|padded| E::from_f32(padding_energy * *padded as f32))
+            .collect();
+        let to_coarse = move |sim_state: &SimState<E>| {
+            assert_eq!(
+                sim_state.energies.len(),
+                coarse_indices.len(),
+                "The state is not a valid state of the downsampled world"
+            );
+            let mut energies = initial_energies.clone();
+            for (energy, coarse_index) in sim_state.energies.iter().zip(coarse_indices.iter()) {
+                energies[*coarse_index] += *energy;
+            }
+            return SimState { energies };
+        };
+
+        return (world, to_coarse);
+    }
+
+    fn paint_brush(&mut self, material: Material, brush: &dyn CellIterator) {
+        let index: u16 = match self.material_map.iter().position(|m| *m == material) {
+            Some(i) => i as u16,
+            None => {
+                let new_index = self.material_map.len();
+                assert!(
+                    new_index <= u16::MAX as usize,
+                    "There can be at most 65536 distinct materials present in a simulation."
+                );
+                self.material_map.push(material);
+                new_index as u16
+            }
+        };
+
+        // Paint whole rows of cells at a time, as they are contiguous in the material buffer
+        let bounds = (self.x_size, self.y_size, self.z_size);
+        for (xs, y, z) in brush.clamped_row_iter(self.cell_size, bounds) {
+            if let Some(start) = self.get_pos_index(xs.start, y, z) {
+                let end = start + xs.end.min(self.x_size) - xs.start;
+                self.materials[start..end].fill(index);
+            }
+        }
+    }
+
+    /// Hold the cells within a brush at a fixed temperature in kelvin
+    ///
+    /// Unlike [SimWorld::set_sim_state_temperature], which only sets an initial condition, fixed
+    /// temperatures are re-imposed by the runners after every timestep. Later calls override
+    /// earlier ones where brushes overlap.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material::{self, Material}, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// let conductor = Material {
+    ///     thermal_conductivity: [(0.0, 0.0, 400.0); 3],
+    ///     ..material::COPPER
+    /// };
+    /// let mut world = SimWorldBuilder::new(0.05, 0.01, 0.01)
+    ///     .with_material(conductor, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.01, 0.01)))
+    ///     .build(0.01);
+    /// let hot_end = AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01);
+    /// let cold_end = AABBVolume::new(0.04, 0.0, 0.0, 0.05, 0.01, 0.01);
+    /// world.set_fixed_temperature(&hot_end, 400.0);
+    /// world.set_fixed_temperature(&cold_end, 300.0);
+    ///
+    /// let mut state = world.get_blank_sim_state();
+    /// state = world.set_sim_state_temperature(state, 300.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.05, 0.01, 0.01)).unwrap();
+    ///
+    /// let runner = CPUSimRunner::default();
+    /// let timestep = world.max_stable_timestep();
+    /// let advance = runner.advance_to_steady_state(&world, &state, timestep, 0.0001, 100_000);
+    /// let (result, _) = futures::executor::block_on(advance).unwrap();
+    ///
+    /// // The ends hold their temperatures, with a linear gradient between them
+    /// for (x, expected) in [400.0, 375.0, 350.0, 325.0, 300.0].into_iter().enumerate() {
+    ///     let temperature = world.sample_voxel_temperature(&result, x, 0, 0).unwrap();
+    ///     assert!((temperature - expected).abs() < 0.1);
+    /// }
+    /// ```
+    pub fn set_fixed_temperature(&mut self, brush: &impl CellIterator, temperature: f32) {
+        for index in brush
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
+            .filter_map(|x| self.get_pos_index(x.0, x.1, x.2))
+            .collect::<Vec<usize>>()
+        {
+            self.fixed_temperatures.insert(index, temperature);
+        }
+    }
+
+    /// Get the temperature in kelvin a cell index is held at, None if the cell is not held at a
+    /// fixed temperature
+    pub fn get_fixed_temperature(&self, index: usize) -> Option<f32> {
+        self.fixed_temperatures.get(&index).copied()
+    }
+
+    /// Release every cell held at a fixed temperature
+    pub fn clear_fixed_temperatures(&mut self) {
+        self.fixed_temperatures.clear();
+    }
+
+    /// Reset every cell held at a fixed temperature back to that temperature
+    ///
+    /// Runners call this at the end of every timestep. Panics if the state is not a valid state
+    /// of this world.
+    pub fn apply_fixed_temperatures<E: Float>(&self, sim_state: &mut SimState<E>) {
+        let cell_volume = self.get_cell_volume() as f32;
+        for (index, temperature) in self.fixed_temperatures.iter() {
+            let cell_material = self.material_map[self.materials[*index] as usize];
+            sim_state.energies[*index] =
+                E::from_f32(cell_material.energy_in_volume(*temperature, cell_volume));
+        }
+    }
+
+    /// Add a volumetric heat source in W / m^3 to the cells within a brush, negative values act
+    /// as heat sinks
+    ///
+    /// Sources persist across timesteps, with the runners adding their energy every timestep.
+    /// Where brushes overlap the sources are summed.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let block = AABBVolume::new(0.0, 0.0, 0.0, 0.1, 0.1, 0.1);
+    /// let mut world = SimWorldBuilder::new(0.1, 0.1, 0.1)
+    ///     .with_material(material::ALUMINUM, Box::new(block.clone()))
+    ///     .build(0.05);
+    /// // A 1 kW heater in the center of the block
+    /// world.add_heat_source(&AABBVolume::new(0.05, 0.05, 0.05, 0.1, 0.1, 0.1), 1000.0 / 0.05_f32.powi(3));
+    ///
+    /// let mut state = world.get_blank_sim_state();
+    /// state = world.set_sim_state_temperature(state, 300.0, &block).unwrap();
+    ///
+    /// // Energy rises linearly with time
+    /// let runner = CPUSimRunner::default();
+    /// let initial = world.total_energy(&state);
+    /// let after_10 = block_on(runner.advance_simulation(&world, &state, 10.0, 1.0)).unwrap();
+    /// let after_20 = block_on(runner.advance_simulation(&world, &after_10, 10.0, 1.0)).unwrap();
+    /// assert!((world.total_energy(&after_10) - initial - 10_000.0).abs() < 50.0);
+    /// assert!((world.total_energy(&after_20) - initial - 20_000.0).abs() < 100.0);
+    /// ```
+    pub fn add_heat_source(&mut self, brush: &impl CellIterator, watts_per_m3: f32) {
+        for index in brush
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
+            .filter_map(|x| self.get_pos_index(x.0, x.1, x.2))
+            .collect::<Vec<usize>>()
+        {
+            *self.heat_sources.entry(index).or_insert(0.0) += watts_per_m3;
+        }
+    }
+
+    /// Remove every heat source, including scheduled heat sources
+    pub fn clear_heat_sources(&mut self) {
+        self.heat_sources.clear();
+        self.scheduled_heat_sources.clear();
+    }
+
+    /// Get the volumetric heat source in W / m^3 at a cell index, zero if the cell has none
+    ///
+    /// Scheduled heat sources are not included, see [SimWorld::add_scheduled_heat_source].
+    pub fn get_heat_source(&self, index: usize) -> f32 {
+        self.heat_sources.get(&index).copied().unwrap_or(0.0)
+    }
+
+    /// Add a volumetric heat source to the cells within a brush which varies over simulated
+    /// time, following a schedule
+    ///
+    /// Runners add the energy a schedule delivers over each timestep at the end of the timestep,
+    /// timing schedules from the start of the advance. Advances continuing a [Checkpoint] or a
+    /// [crate::sensor::SensorArray] are timed from the checkpoint or the array's latest readings.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{HeatSchedule, SimWorldBuilder}, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let block = AABBVolume::new(0.0, 0.0, 0.0, 0.1, 0.1, 0.1);
+    /// let mut world = SimWorldBuilder::new(0.1, 0.1, 0.1)
+    ///     .with_material(material::ALUMINUM, Box::new(block.clone()))
+    ///     .build(0.05);
+    /// // A 1 kW heater in the center of the block, on for the first second
+    /// let heater = AABBVolume::new(0.05, 0.05, 0.05, 0.1, 0.1, 0.1);
+    /// world.add_scheduled_heat_source(&heater, HeatSchedule::pulse(1000.0 / 0.05_f32.powi(3), 0.0, 1.0));
+    ///
+    /// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &block).unwrap();
+    /// let initial = world.total_energy(&state);
+    ///
+    /// // Energy rises while the pulse is on, then plateaus
+    /// let runner = CPUSimRunner::default();
+    /// let energies: Vec<f64> = [0.5, 1.0, 2.0, 4.0]
+    ///     .into_iter()
+    ///     .map(|time| block_on(runner.advance_simulation(&world, &state, time, 0.25)).unwrap())
+    ///     .map(|result| world.total_energy(&result) - initial)
+    ///     .collect();
+    /// assert!((energies[0] - 500.0).abs() < 1.0);
+    /// for energy in &energies[1..] {
+    ///     assert!((energy - 1000.0).abs() < 1.0);
+    /// }
+    /// ```
+    pub fn add_scheduled_heat_source(&mut self, brush: &impl CellIterator, schedule: HeatSchedule) {
+        let cells: Vec<usize> = brush
+            .clamped_cell_iter(self.cell_size, (self.x_size, self.y_size, self.z_size))
+            .filter_map(|x| self.get_pos_index(x.0, x.1, x.2))
+            .collect();
+        self.scheduled_heat_sources.push((schedule, cells));
+    }
+
+    /// Add the energy every scheduled heat source delivers between two simulated times in
+    /// seconds to a simulation state, then re-impose fixed temperatures
+    ///
+    /// Runners call this at the end of every timestep. Panics if the state is not a valid state
+    /// of this world.
+    pub fn apply_scheduled_heat_sources<E: Float>(
+        &self,
+        sim_state: &mut SimState<E>,
+        start_time: f64,
+        end_time: f64,
+    ) {
+        if self.scheduled_heat_sources.is_empty() {
+            return;
+        }
+        let cell_volume = self.get_cell_volume();
+        for (schedule, cells) in self.scheduled_heat_sources.iter() {
+            let energy = E::from_f64(schedule.energy_between(start_time, end_time) * cell_volume);
+            for index in cells.iter() {
+                sim_state.energies[*index] += energy;
+            }
+        }
+        self.apply_fixed_temperatures(sim_state);
+    }
+
+    /// Get the temperature of every cell in a simulation state, in the same order as the cell
+    /// materials. Fails if state has a different bounds size
+    ///
+    /// Temperatures are single precision whatever the precision of the state, see
+    /// [SimWorld::precise_temperature_field] for temperatures at the state's precision.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 0.5, 1.0)))
+    ///     .build(0.25);
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+    /// let state = world
+    ///     .set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything)
+    ///     .unwrap();
+    /// let field = world.temperature_field(&state).unwrap();
+    /// assert_eq!(field.len(), 64);
+    /// assert!(field.iter().all(|t| (t - 300.0).abs() < 0.01));
+    /// ```
+    pub fn temperature_field<E: Float>(
+        &self,
+        sim_state: &SimState<E>,
+    ) -> Result<Vec<f32>, SimStateOppError> {
+        return Ok(self
+            .precise_temperature_field(sim_state)?
+            .into_iter()
+            .map(|t| t.to_f32())
+            .collect());
+    }
+
+    /// Get the temperature of every cell in a simulation state at the state's precision, in the
+    /// same order as the cell materials. Fails if state has a different bounds size
+    ///
+    /// Temperatures are found from energies in double precision, so temperature differences too
+    /// small for single precision are kept in [f64] states. The runners calculate the flow of
+    /// heat from these temperatures.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{SimState, SimWorldBuilder}, material, volume::AABBVolume};
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.01, 0.01);
+    /// let world = SimWorldBuilder::new(0.02, 0.01, 0.01)
+    ///     .with_material(material::COPPER, Box::new(everything.clone()))
+    ///     .build(0.01);
+    /// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 1000.0, &everything).unwrap();
+    ///
+    /// // Warm one cell by a hundred thousandth of a kelvin
+    /// let mut precise_state: SimState<f64> = state.to_precision();
+    /// let warming = material::COPPER.volumetric_heat_capacity() as f64 * world.get_cell_volume() * 1.0e-5;
+    /// precise_state.apply_deltas([warming]);
+    ///
+    /// let field = world.precise_temperature_field(&precise_state).unwrap();
+    /// assert!((field[0] - field[1] - 1.0e-5).abs() < 1.0e-8);
+    /// // Single precision temperatures near 1000 K are 6e-5 K apart, too coarse to keep it
+    /// let single = world.temperature_field(&precise_state).unwrap();
+    /// assert!(((single[0] - single[1]) as f64 - 1.0e-5).abs() > 1.0e-6);
+    /// ```
+    pub fn precise_temperature_field<E: Float>(
+        &self,
+        sim_state: &SimState<E>,
+    ) -> Result<Vec<E>, SimStateOppError> {
+        if !self.is_state_valid(sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+
+        return Ok(self
+            .materials
+            .iter()
+            .zip(sim_state.energies.iter())
+            .map(|(mat_id, energy)| {
+                self.cell_temperature(&self.material_map[*mat_id as usize], *energy)
+            })
+            .collect());
+    }
+
+    /// Iterate over every cell of a simulation state, with its position, material and temperature
+    /// in kelvin, in the same order as the cell materials. Fails if state has a different bounds
+    /// size
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 0.75, 0.5)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 0.25, 0.5)))
+    ///     .build(0.25);
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 0.75, 0.5);
+    /// let state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    ///
+    /// let cells: Vec<_> = world.iter_cells(&state).unwrap().collect();
+    /// assert_eq!(cells.len(), 4 * 3 * 2);
+    /// for (i, (x, y, z, material, temperature)) in cells.into_iter().enumerate() {
+    ///     assert_eq!(world.get_index_pos(i), Some((x, y, z)));
+    ///     assert_eq!(material, if y == 0 { &material::WATER } else { &material::BLANK });
+    ///     assert!((temperature - 300.0).abs() < 0.01);
+    /// }
+    /// ```
+    pub fn iter_cells<'a, E: Float>(
+        &'a self,
+        sim_state: &'a SimState<E>,
+    ) -> Result<impl Iterator<Item = (usize, usize, usize, &'a Material, f32)> + 'a, SimStateOppError>
+    {
+        if !self.is_state_valid(sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+
+        return Ok(self
+            .materials
+            .iter()
+            .zip(sim_state.energies.iter())
+            .enumerate()
+            .map(|(i, (mat_id, energy))| {
+                let (x, y, z) = (
+                    i % self.x_size,
+                    (i / self.x_size) % self.y_size,
+                    i / (self.x_size * self.y_size),
+                );
+                let material = &self.material_map[*mat_id as usize];
+                (
+                    x,
+                    y,
+                    z,
+                    material,
+                    self.cell_temperature(material, *energy).to_f32(),
+                )
+            }));
+    }
+
+    /// Get the total thermal energy in joules held in a simulation state
+    ///
+    /// Every cell is included, including [material::BLANK] cells, so that this can be used to
+    /// check energy conservation.
+    pub fn total_energy<E: Float>(&self, sim_state: &SimState<E>) -> f64 {
+        sim_state.energies.iter().map(|e| e.to_f64()).sum()
+    }
+
+    /// Get the fractional change in total energy between two simulation states, as
+    /// `(after - before) / before`. Returns None if either state is of the wrong size or the
+    /// first state holds no energy
+    ///
+    /// In a closed world, with insulated boundaries and no heat sources, fixed temperatures or
+    /// convection, every runner should keep this near zero.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// // Materials with very different, temperature dependent conductivities, so that heat only
+    /// // stays conserved if each flow leaves one cell exactly as it enters the other
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.04, 0.03, 0.02);
+    /// let world = SimWorldBuilder::new(0.04, 0.03, 0.02)
+    ///     .with_material(material::WATER, Box::new(everything.clone()))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.03, 0.02)))
+    ///     .build(0.01);
+    /// let mut before = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    /// before = world.set_sim_state_temperature(before, 350.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.01, 0.01, 0.01)).unwrap();
+    ///
+    /// let runner = CPUSimRunner::default();
+    /// let after = block_on(runner.advance_simulation(&world, &before, 10.0, world.max_stable_timestep())).unwrap();
+    /// assert!(world.energy_drift(&before, &after).unwrap().abs() < 1e-6);
+    /// world.assert_energy_conserved(&before, &after, 1e-6);
+    /// ```
+    pub fn energy_drift<E: Float>(&self, before: &SimState<E>, after: &SimState<E>) -> Option<f64> {
+        if !self.is_state_valid(before) || !self.is_state_valid(after) {
+            return None;
+        }
+        let before = self.total_energy(before);
+        if before == 0.0 {
+            return None;
+        }
+        return Some((self.total_energy(after) - before) / before);
+    }
+
+    /// Assert that the total energy of two simulation states differs by no more than a fraction
+    /// `tolerance` of the first, see [SimWorld::energy_drift]
+    ///
+    /// Panics if the drift is larger than the tolerance, or if it can not be calculated.
+    pub fn assert_energy_conserved<E: Float>(
+        &self,
+        before: &SimState<E>,
+        after: &SimState<E>,
+        tolerance: f64,
+    ) {
+        let drift = self
+            .energy_drift(before, after)
+            .expect("Energy drift is only defined for valid states holding energy");
+        assert!(
+            drift.abs() <= tolerance,
+            "Energy changed by a fraction {drift}, more than the tolerance of {tolerance}"
+        );
+    }
+
+    /// Get the mass weighted mean temperature in kelvin of a simulation state. Returns None if
+    /// the state is of the wrong size or the world has no non blank cells
+    ///
+    /// [material::BLANK] cells represent empty space, so they are excluded.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// // A world of half water, half copper
+    /// let world = SimWorldBuilder::new(2.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .with_material(material::COPPER, Box::new(AABBVolume::new(1.0, 0.0, 0.0, 2.0, 1.0, 1.0)))
+    ///     .build(1.0);
+    /// let mut state = world.get_blank_sim_state();
+    /// let water = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0);
+    /// let copper = AABBVolume::new(1.0, 0.0, 0.0, 2.0, 1.0, 1.0);
+    /// state = world.set_sim_state_temperature(state, 300.0, &water).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &copper).unwrap();
+    ///
+    /// let (min, max) = world.temperature_extremes(&state).unwrap();
+    /// assert!((min - 300.0).abs() < 0.01 && (max - 400.0).abs() < 0.01);
+    ///
+    /// let expected_mean = (1000.0 * 300.0 + 8960.0 * 400.0) / (1000.0 + 8960.0);
+    /// assert!((world.mean_temperature(&state).unwrap() - expected_mean).abs() < 0.01);
+    /// ```
+    pub fn mean_temperature<E: Float>(&self, sim_state: &SimState<E>) -> Option<f64> {
+        let cell_volume = self.get_cell_volume() as f32;
+        let (weighted_sum, total_mass) = self
+            .temperature_field(sim_state)
+            .ok()?
+            .into_iter()
+            .zip(self.materials.iter())
+            .map(|(temp, mat_id)| (temp, &self.material_map[*mat_id as usize]))
+            .filter(|(_, material)| **material != material::BLANK)
+            .fold((0.0, 0.0), |(sum, mass), (temp, material)| {
+                let cell_mass = (material.get_density(temp) * cell_volume) as f64;
+                (sum + temp as f64 * cell_mass, mass + cell_mass)
+            });
+        if total_mass <= 0.0 {
+            return None;
+        }
+        return Some(weighted_sum / total_mass);
+    }
+
+    /// Get the (minimum, maximum) temperature in kelvin of a simulation state. Returns None if
+    /// the state is of the wrong size or the world has no non blank cells
+    ///
+    /// [material::BLANK] cells represent empty space, so they are excluded.
+    pub fn temperature_extremes<E: Float>(&self, sim_state: &SimState<E>) -> Option<(f32, f32)> {
+        return self
+            .temperature_field(sim_state)
+            .ok()?
+            .into_iter()
+            .zip(self.materials.iter())
+            .filter(|(_, mat_id)| self.material_map[**mat_id as usize] != material::BLANK)
+            .map(|(temp, _)| (temp, temp))
+            .reduce(|(min, max), (lo, hi)| (min.min(lo), max.max(hi)));
+    }
+
+    /// Get the minimum, mean and maximum temperatures in kelvin of the cells filled with a given
+    /// material. Returns None if the material is not present in the world or the simulation state
+    /// is of the wrong size
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let copper = AABBVolume::new(0.0, 0.0, 0.0, 1.0, 2.0, 1.0);
+    /// let world = SimWorldBuilder::new(3.0, 2.0, 1.0)
+    ///     .with_material(material::AIR, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 3.0, 2.0, 1.0)))
+    ///     .with_material(material::COPPER, Box::new(copper.clone()))
+    ///     .build(1.0);
+    /// let mut state = world.get_blank_sim_state();
+    /// state = world.set_sim_state_temperature(state, 290.0, &AABBVolume::new(0.0, 0.0, 0.0, 3.0, 2.0, 1.0)).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &copper).unwrap();
+    /// state = world.set_sim_state_temperature(state, 500.0, &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)).unwrap();
+    ///
+    /// let (min, mean, max) = world.material_temperature_stats(&state, &material::COPPER).unwrap();
+    /// assert!((min - 400.0).abs() < 0.01 && (mean - 450.0).abs() < 0.01 && (max - 500.0).abs() < 0.01);
+    /// assert!(world.material_temperature_stats(&state, &material::WATER).is_none());
+    /// ```
+    pub fn material_temperature_stats<E: Float>(
+        &self,
+        sim_state: &SimState<E>,
+        material: &Material,
+    ) -> Option<(f32, f32, f32)> {
+        let material_index = self.material_map.iter().position(|m| m == material)? as u16;
+        let (min, sum, max, count) = self
+            .temperature_field(sim_state)
+            .ok()?
+            .into_iter()
+            .zip(self.materials.iter())
+            .filter(|(_, mat_id)| **mat_id == material_index)
+            .fold(
+                (f32::INFINITY, 0.0f64, f32::NEG_INFINITY, 0usize),
+                |(min, sum, max, count), (temp, _)| {
+                    (min.min(temp), sum + temp as f64, max.max(temp), count + 1)
+                },
+            );
+        if count == 0 {
+            return None;
+        }
+        return Some((min, (sum / count as f64) as f32, max));
+    }
+
+    /// Samples the temperature at the voxel closest to the given point. Returns None if given
+    /// point is out of bounds or simulation state is of the wrong size
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(1.0, 1.0, 1.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)))
+    ///     .build(0.1);
+    /// let state = world
+    ///     .set_sim_state_temperature(
+    ///         world.get_blank_sim_state(),
+    ///         350.0,
+    ///         &AABBVolume::new(0.0, 0.0, 0.0, 0.5, 1.0, 1.0),
+    ///     )
+    ///     .unwrap();
+    /// let heated = world.sample_temperature(&state, 0.25, 0.5, 0.5).unwrap();
+    /// assert!((heated - 350.0).abs() < 0.01);
+    /// assert_eq!(world.sample_temperature(&state, 0.75, 0.5, 0.5), Some(0.0));
+    /// assert_eq!(world.sample_temperature(&state, 1.5, 0.5, 0.5), None);
+    /// assert_eq!(world.sample_temperature(&state, f64::NAN, 0.5, 0.5), None);
+    /// ```
+    pub fn sample_temperature<E: Float>(
+        &self,
+        sim_state: &SimState<E>,
+        x: f64,
+        y: f64,
+        z: f64,
+    ) -> Option<f32> {
+        let (x, y, z) = self.get_point_voxel(x, y, z)?;
+        self.sample_voxel_temperature(sim_state, x, y, z)
+    }
+
     /// Samples the temperature of a given voxel. Returns None if given position is out of bounds
     /// or simulation state is of the wrong size
-    fn sample_voxel_temperature(
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::SimWorldBuilder, material, volume::AABBVolume};
+    /// let world = SimWorldBuilder::new(2.0, 2.0, 2.0)
+    ///     .with_material(material::WATER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 2.0, 1.0, 2.0)))
+    ///     .build(1.0);
+    /// let state = world
+    ///     .set_sim_state_temperature(
+    ///         world.get_blank_sim_state(),
+    ///         300.0,
+    ///         &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0),
+    ///     )
+    ///     .unwrap();
+    /// let temperature = world.sample_voxel_temperature(&state, 0, 0, 0).unwrap();
+    /// assert!((temperature - 300.0).abs() < 0.01);
+    /// assert_eq!(world.sample_voxel_temperature(&state, 1, 0, 0), Some(0.0));
+    /// assert_eq!(world.sample_voxel_temperature(&state, 2, 0, 0), None);
+    /// ```
+    pub fn sample_voxel_temperature<E: Float>(
         &self,
-        sim_state: &SimState,
+        sim_state: &SimState<E>,
         x: usize,
         y: usize,
         z: usize,
     ) -> Option<f32> {
-        if sim_state.energies.len() != self.materials.len() {
+        if !self.is_state_valid(sim_state) {
             return None;
         }
-        if x >= self.x_size || y >= self.y_size || z > self.z_size {
-            let index = x + y * self.x_size + z * self.x_size * self.y_size;
-            let cell_mat_id = self
-                .materials
-                .get(index)
-                .expect("Indicies are pre-verified");
-            let cell_material = self
-                .material_map
-                .get(*cell_mat_id as usize)
-                .expect("Cell material IDs are static and must be valid");
-            let cell_energy = sim_state
-                .energies
-                .get(*cell_mat_id as usize)
-                .expect("State is already known to be correct size");
-            let cell_mass = self.cell_size.powf(3.0) as f32 * cell_material.density;
+        let index = self.get_pos_index(x, y, z)?;
+        let cell_material = self.get_voxel_material(x, y, z)?;
+        let cell_energy = sim_state.energies[index];
+        return Some(self.cell_temperature(cell_material, cell_energy).to_f32());
+    }
 
-            return Some(cell_energy / (cell_mass * cell_material.specific_heat) as f32);
-        } else {
-            return None;
+    /// Get the temperature in kelvin of a cell of a material holding the given energy in joules.
+    /// Cells of materials without heat capacity are at the ambient temperature
+    fn cell_temperature<E: Float>(&self, material: &Material, energy: E) -> E {
+        if !material.has_heat_capacity() {
+            return E::from_f32(self.ambient_temperature);
         }
+        return E::from_f64(
+            material.temperature_in_volume_f64(energy.to_f64(), self.get_cell_volume()),
+        );
+    }
+}
+
+/// Floating point types which simulation energies can be stored in
+///
+/// Implemented for [f32] and [f64]. See [SimState] for the tradeoffs between them.
+pub trait Float:
+    Copy
+    + Default
+    + PartialOrd
+    + std::fmt::Debug
+    + std::fmt::Display
+    + std::ops::Add<Output = Self>
+    + std::ops::Sub<Output = Self>
+    + std::ops::Mul<Output = Self>
+    + std::ops::AddAssign
+    + std::iter::Sum
+    + Send
+    + Sync
+    + 'static
+{
+    /// Convert from an [f32], rounding if needed
+    fn from_f32(value: f32) -> Self;
+    /// Convert from an [f64], rounding if needed
+    fn from_f64(value: f64) -> Self;
+    /// Convert to an [f32], rounding if needed
+    fn to_f32(self) -> f32;
+    /// Convert to an [f64]
+    fn to_f64(self) -> f64;
+}
+
+impl Float for f32 {
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Float for f64 {
+    fn from_f32(value: f32) -> Self {
+        value as f64
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn to_f32(self) -> f32 {
+        self as f32
+    }
+
+    fn to_f64(self) -> f64 {
+        self
     }
 }
 
 /// Represents the distribution of thermal energy in a simulation world at a given state in time
 ///
 /// Has little meaning on it's own, is only usefull in the context of a [SimWorld]
+///
+/// Energies are stored as [f32] by default. Storing them as [f64] doubles the memory used by a
+/// state, but small changes in energy are no longer lost to rounding when added to large cell
+/// energies. This matters for long simulations, or worlds with large differences between cell
+/// energies.
+///
+/// Temperatures are found from the energies at the chosen precision, see
+/// [SimWorld::precise_temperature_field]. The CPU, threaded, rayon and implicit runners calculate
+/// temperature differences and energy flows at the chosen precision too, so a temperature
+/// difference too small for single precision to resolve, around 1e-4 K at 1000 K, still drives
+/// a flow in an [f64] state. The SIMD and GPU runners calculate each flow in single precision,
+/// and only sum the flows into the state at the chosen precision.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::{SimState, SimWorldBuilder}, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+/// # use futures::executor::block_on;
+/// let everything = AABBVolume::new(0.0, 0.0, 0.0, 0.06, 0.06, 0.06);
+/// let world = SimWorldBuilder::new(0.06, 0.06, 0.06)
+///     .with_material(material::COPPER, Box::new(everything.clone()))
+///     .build(0.02);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.02, 0.02)).unwrap();
+/// let precise_state: SimState<f64> = state.to_precision();
+///
+/// // Advance ten thousand steps at each precision
+/// let runner = CPUSimRunner::default();
+/// let timestep = world.max_stable_timestep() / 2.0;
+/// let advance_time = timestep * 10_000.0;
+/// let single = block_on(runner.advance_simulation(&world, &state, advance_time, timestep)).unwrap();
+/// let double = block_on(runner.advance_simulation(&world, &precise_state, advance_time, timestep)).unwrap();
+///
+/// let error = |before: f64, after: f64| ((after - before) / before).abs();
+/// let single_error = error(world.total_energy(&state), world.total_energy(&single));
+/// let double_error = error(world.total_energy(&precise_state), world.total_energy(&double));
+/// assert!(double_error < single_error);
+/// ```
 #[derive(Debug, Clone)]
-pub struct SimState {
-    energies: Vec<f32>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimState<E: Float = f32> {
+    energies: Vec<E>,
+}
+
+#[cfg(feature = "serde")]
+impl<E: Float + serde::Serialize + serde::de::DeserializeOwned> SimState<E> {
+    /// Save the state to a binary checkpoint file, requires the `serde` feature
+    ///
+    /// See [SimWorld::save] for an example.
+    pub fn save(&self, path: &std::path::Path) -> Result<(), CheckpointError> {
+        return save_checkpoint(self, path);
+    }
+
+    /// Load a state from a binary checkpoint file written by [SimState::save], requires the
+    /// `serde` feature
+    pub fn load(path: &std::path::Path) -> Result<SimState<E>, CheckpointError> {
+        return load_checkpoint(path);
+    }
 }
 
-impl SimState {
+impl<E: Float> SimState<E> {
     /// Get a non-mutable reference to the energies in this state
-    pub fn get_energies<'a>(&'a self) -> &'a [f32] {
+    pub fn get_energies(&self) -> &[E] {
         self.energies.as_slice()
     }
 
     /// Applys a heat delta to all values in this sim state
-    pub fn apply_deltas<T: IntoIterator<Item = f32>>(&mut self, iter: T) {
+    ///
+    /// Deltas are applied to energies in order, so the deltas are expected to be the same length
+    /// as the state. Any extra deltas are ignored, and any cells without a delta are unchanged.
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::SimState;
+    /// let mut state: SimState = vec![1.0, 2.0, 3.0].into_iter().collect();
+    /// state.apply_deltas(vec![0.5, -1.0, 2.0]);
+    /// assert_eq!(state.get_energies(), &[1.5, 1.0, 5.0]);
+    /// assert_eq!(state.get_energies().iter().sum::<f32>(), 7.5);
+    /// ```
+    pub fn apply_deltas<T: IntoIterator<Item = E>>(&mut self, iter: T) {
         for (cur, del) in self.energies.iter_mut().zip(iter) {
             *cur += del;
         }
     }
+
+    /// Check whether every energy in this state is finite, neither NaN nor infinite
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::SimState;
+    /// let state: SimState = vec![1.0, 2.0, 3.0].into_iter().collect();
+    /// assert!(state.is_finite());
+    ///
+    /// let unstable: SimState = vec![1.0, f32::NAN, f32::INFINITY].into_iter().collect();
+    /// assert!(!unstable.is_finite());
+    /// assert_eq!(unstable.first_non_finite(), Some(1));
+    /// ```
+    pub fn is_finite(&self) -> bool {
+        self.first_non_finite().is_none()
+    }
+
+    /// Get the index of the first cell whose energy is NaN or infinite, None if every energy is
+    /// finite
+    pub fn first_non_finite(&self) -> Option<usize> {
+        self.energies.iter().position(|e| !e.to_f64().is_finite())
+    }
+
+    /// Convert this state to store its energies at a different precision
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::world::SimState;
+    /// let state: SimState = vec![1.5, 2.0].into_iter().collect();
+    /// let precise: SimState<f64> = state.to_precision();
+    /// assert_eq!(precise.get_energies(), &[1.5, 2.0]);
+    /// ```
+    pub fn to_precision<F: Float>(&self) -> SimState<F> {
+        SimState {
+            energies: self
+                .energies
+                .iter()
+                .map(|e| F::from_f64(e.to_f64()))
+                .collect(),
+        }
+    }
 }
 
-impl FromIterator<f32> for SimState {
-    fn from_iter<T: IntoIterator<Item = f32>>(iter: T) -> Self {
+impl<E: Float> FromIterator<E> for SimState<E> {
+    fn from_iter<T: IntoIterator<Item = E>>(iter: T) -> Self {
         SimState {
             energies: Vec::from_iter(iter),
         }
     }
 }
+
+/// A simulation saved part way through, holding the world, the state reached, the simulated
+/// time elapsed in seconds and the timestep in seconds it was advanced with
+///
+/// Checkpoints can be continued with [crate::runner::SimRunner::resume], and saved to a file to
+/// continue a simulation which was interrupted.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint<E: Float = f32> {
+    world: SimWorld,
+    state: SimState<E>,
+    sim_time: f64,
+    timestep: f64,
+}
+
+impl<E: Float> Checkpoint<E> {
+    /// Create a new checkpoint of a simulation
+    pub fn new(world: SimWorld, state: SimState<E>, sim_time: f64, timestep: f64) -> Checkpoint<E> {
+        Checkpoint {
+            world,
+            state,
+            sim_time,
+            timestep,
+        }
+    }
+
+    /// Get the world being simulated
+    pub fn get_world(&self) -> &SimWorld {
+        &self.world
+    }
+
+    /// Get the state the simulation reached
+    pub fn get_state(&self) -> &SimState<E> {
+        &self.state
+    }
+
+    /// Get the simulated time elapsed in seconds
+    pub fn get_sim_time(&self) -> f64 {
+        self.sim_time
+    }
+
+    /// Get the timestep in seconds the simulation was advanced with
+    pub fn get_timestep(&self) -> f64 {
+        self.timestep
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<E: Float + serde::Serialize + serde::de::DeserializeOwned> Checkpoint<E> {
+    /// Save the checkpoint to a binary checkpoint file, requires the `serde` feature
+    ///
+    /// ## Example
+    /// ```
+    /// # use thermal_rs::{world::{Checkpoint, SimWorldBuilder}, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+    /// # use futures::executor::block_on;
+    /// let everything = AABBVolume::new(0.0, 0.0, 0.0, 4.0, 1.0, 1.0);
+    /// let world = SimWorldBuilder::new(4.0, 1.0, 1.0)
+    ///     .with_material(material::COPPER, Box::new(everything.clone()))
+    ///     .build(1.0);
+    /// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &everything).unwrap();
+    /// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 1.0, 1.0, 1.0)).unwrap();
+    /// let runner = CPUSimRunner::default();
+    /// let uninterrupted = block_on(runner.advance_simulation(&world, &state, 10.0, 1.0)).unwrap();
+    ///
+    /// // Run part of the way, save and load the checkpoint, then run the rest of the way
+    /// let start = Checkpoint::new(world, state, 0.0, 1.0);
+    /// let partial = block_on(runner.resume(&start, 4.0, 1.0)).unwrap();
+    /// let path = std::env::temp_dir().join("thermal_rs_doctest_checkpoint.bin");
+    /// partial.save(&path).unwrap();
+    /// let loaded: Checkpoint = Checkpoint::load(&path).unwrap();
+    /// let finished = block_on(runner.resume(&loaded, 6.0, loaded.get_timestep())).unwrap();
+    ///
+    /// assert_eq!(finished.get_sim_time(), 10.0);
+    /// assert_eq!(finished.get_state().get_energies(), uninterrupted.get_energies());
+    /// ```
+    pub fn save(&self, path: &std::path::Path) -> Result<(), CheckpointError> {
+        return save_checkpoint(self, path);
+    }
+
+    /// Load a checkpoint from a binary checkpoint file written by [Checkpoint::save], requires
+    /// the `serde` feature
+    pub fn load(path: &std::path::Path) -> Result<Checkpoint<E>, CheckpointError> {
+        return load_checkpoint(path);
+    }
+}
+
+/// The number of cells along each edge of a [SparseSimState] chunk
+pub const SPARSE_CHUNK_SIZE: usize = 16;
+
+/// Sparse storage for a snapshot of the thermal energy within a simulation world
+///
+/// Cells are grouped into cubic chunks of [SPARSE_CHUNK_SIZE] cells along each edge, and only
+/// chunks containing a cell with an energy other than the state's fill energy are stored. For
+/// worlds which are mostly a single uniform material, such as a small part in a large volume of
+/// air, this uses far less memory than a dense [SimState]. Chunks at the edges of the world are
+/// cut to the cells inside it, so a stored chunk uses as much memory as the same cells in a dense
+/// state plus the overhead of a hash map entry, and a state where most chunks differ from the
+/// fill energy uses slightly more memory than a dense state.
+///
+/// Accessing a cell requires a hash map lookup, so reading or updating every cell is slower than
+/// with a dense state.
+///
+/// No runner accepts a sparse state yet. Advancing one means converting it with
+/// [SparseSimState::to_dense], which allocates every cell of the world, so sparse states only save
+/// memory while storing snapshots between runs, not while a simulation is running.
+///
+/// ## Example
+/// ```
+/// # use thermal_rs::{world::{SimWorldBuilder, SparseSimState}, material, volume::AABBVolume, runner::{SimRunner, cpu::CPUSimRunner}};
+/// let air = AABBVolume::new(0.0, 0.0, 0.0, 0.4, 0.4, 0.4);
+/// let world = SimWorldBuilder::new(0.4, 0.4, 0.4)
+///     .with_material(material::AIR, Box::new(air.clone()))
+///     .with_material(material::COPPER, Box::new(AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.02, 0.02)))
+///     .build(0.01);
+/// let mut state = world.set_sim_state_temperature(world.get_blank_sim_state(), 300.0, &air).unwrap();
+/// state = world.set_sim_state_temperature(state, 400.0, &AABBVolume::new(0.0, 0.0, 0.0, 0.02, 0.02, 0.02)).unwrap();
+///
+/// // Only the chunk around the hot copper differs from the surrounding air
+/// let air_energy = state.get_energies()[world.get_pos_index(39, 39, 39).unwrap()];
+/// let sparse = SparseSimState::from_dense(&world, &state, air_energy).unwrap();
+/// assert_eq!(sparse.get_chunk_count(), 1);
+///
+/// // Runners advance the dense form of the sparse state, with identical results
+/// let runner = CPUSimRunner::default();
+/// let dense_result = runner.advance_step(&world, state, 0.1);
+/// let sparse_result = runner.advance_step(&world, sparse.to_dense(), 0.1);
+/// assert_eq!(dense_result.get_energies(), sparse_result.get_energies());
+///
+/// let round_trip = SparseSimState::from_dense(&world, &sparse_result, air_energy).unwrap();
+/// assert_eq!(round_trip.to_dense().get_energies(), dense_result.get_energies());
+///
+/// // The 40 cell world ends part way through its third chunk along each axis, that chunk only
+/// // stores the cells inside the world
+/// let mut edge = SparseSimState::new(&world, air_energy);
+/// let last = world.get_pos_index(39, 39, 39).unwrap();
+/// edge.apply_deltas((0..=last).map(|i| if i == last { 1.0 } else { 0.0 }));
+/// assert_eq!(edge.get_chunk_count(), 1);
+/// assert_eq!(edge.get_energy(last), Some(air_energy + 1.0));
+/// assert_eq!(edge.get_energy(last - 1), Some(air_energy));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SparseSimState<E: Float = f32> {
+    x_size: usize,
+    y_size: usize,
+    z_size: usize,
+    fill: E,
+    chunks: std::collections::HashMap<(usize, usize, usize), Vec<E>>,
+}
+
+impl<E: Float> SparseSimState<E> {
+    /// Create a new sparse state for a world, with every cell at the given fill energy
+    pub fn new(world: &SimWorld, fill: E) -> SparseSimState<E> {
+        SparseSimState {
+            x_size: world.x_size,
+            y_size: world.y_size,
+            z_size: world.z_size,
+            fill,
+            chunks: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Create a sparse state from a dense state, only storing chunks containing a cell with an
+    /// energy other than the fill energy. Returns an error if the dense state is of the wrong size
+    pub fn from_dense(
+        world: &SimWorld,
+        sim_state: &SimState<E>,
+        fill: E,
+    ) -> Result<SparseSimState<E>, SimStateOppError> {
+        if !world.is_state_valid(sim_state) {
+            return Err(SimStateOppError::StateSizeMissmatch);
+        }
+        let mut sparse = SparseSimState::new(world, fill);
+        for (index, energy) in sim_state.energies.iter().enumerate() {
+            if *energy != fill {
+                *sparse.get_energy_mut(index) = *energy;
+            }
+        }
+        return Ok(sparse);
+    }
+
+    /// Convert this state to a dense [SimState], allocating every cell of the world
+    pub fn to_dense(&self) -> SimState<E> {
+        return self.get_energies().into_iter().collect();
+    }
+
+    /// Get the number of chunks currently stored
+    pub fn get_chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Get the energy of the cell at a given index. Returns none if the index is out of bounds
+    pub fn get_energy(&self, index: usize) -> Option<E> {
+        let (chunk, offset) = self.chunk_position(index)?;
+        return Some(match self.chunks.get(&chunk) {
+            Some(cells) => cells[offset],
+            None => self.fill,
+        });
+    }
+
+    /// Get the energies of every cell in this state, in the same order as [SimState::get_energies]
+    ///
+    /// Unlike [SimState::get_energies] this is not a cheap borrow, it allocates a dense [Vec] of
+    /// every cell of the world. Use [SparseSimState::get_energy] to read single cells.
+    pub fn get_energies(&self) -> Vec<E> {
+        return (0..self.x_size * self.y_size * self.z_size)
+            .filter_map(|i| self.get_energy(i))
+            .collect();
+    }
+
+    /// Applys a heat delta to all values in this sim state, with the same semantics as
+    /// [SimState::apply_deltas]
+    ///
+    /// Chunks are only created when a non zero delta is applied to one of their cells, call
+    /// [SparseSimState::compact] to drop chunks which have returned to the fill energy.
+    pub fn apply_deltas<T: IntoIterator<Item = E>>(&mut self, iter: T) {
+        let cell_count = self.x_size * self.y_size * self.z_size;
+        for (index, delta) in iter.into_iter().take(cell_count).enumerate() {
+            if delta != E::default() {
+                *self.get_energy_mut(index) += delta;
+            }
+        }
+    }
+
+    /// Drop every stored chunk where all cells are at the fill energy
+    pub fn compact(&mut self) {
+        let fill = self.fill;
+        self.chunks
+            .retain(|_, cells| cells.iter().any(|e| *e != fill));
+    }
+
+    /// Get the energy of a cell to modify, storing its chunk if it is not already stored
+    fn get_energy_mut(&mut self, index: usize) -> &mut E {
+        let (chunk, offset) = self
+            .chunk_position(index)
+            .expect("Indices are within the state");
+        let fill = self.fill;
+        let (x, y, z) = self.chunk_extent(chunk);
+        let cells = self
+            .chunks
+            .entry(chunk)
+            .or_insert_with(|| vec![fill; x * y * z]);
+        return &mut cells[offset];
+    }
+
+    /// Get the number of cells along each axis of a chunk, which is less than
+    /// [SPARSE_CHUNK_SIZE] for chunks at the far edges of the world
+    fn chunk_extent(&self, chunk: (usize, usize, usize)) -> (usize, usize, usize) {
+        let extent =
+            |chunk: usize, size: usize| SPARSE_CHUNK_SIZE.min(size - chunk * SPARSE_CHUNK_SIZE);
+        return (
+            extent(chunk.0, self.x_size),
+            extent(chunk.1, self.y_size),
+            extent(chunk.2, self.z_size),
+        );
+    }
+
+    fn chunk_position(&self, index: usize) -> Option<((usize, usize, usize), usize)> {
+        if index >= self.x_size * self.y_size * self.z_size {
+            return None;
+        }
+        let x = index % self.x_size;
+        let y = (index / self.x_size) % self.y_size;
+        let z = index / (self.x_size * self.y_size);
+        let chunk = (
+            x / SPARSE_CHUNK_SIZE,
+            y / SPARSE_CHUNK_SIZE,
+            z / SPARSE_CHUNK_SIZE,
+        );
+        let (x_extent, y_extent, _) = self.chunk_extent(chunk);
+        let offset = x % SPARSE_CHUNK_SIZE
+            + (y % SPARSE_CHUNK_SIZE) * x_extent
+            + (z % SPARSE_CHUNK_SIZE) * x_extent * y_extent;
+        return Some((chunk, offset));
+    }
+}